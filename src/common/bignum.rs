@@ -0,0 +1,123 @@
+use std::{cmp::Ordering, fmt};
+
+// Just enough of an arbitrary-precision unsigned integer for counting:
+// addition, multiplication by a small number, comparison and printing.
+// Little-endian base 2^32 limbs, with no trailing zero limbs.
+//
+// Shared between days with `#[path = "../common/bignum.rs"] mod bignum;`,
+// so not every day uses every method.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct BigUint {
+    limbs: Vec<u32>,
+}
+
+impl BigUint {
+    pub fn zero() -> Self {
+        BigUint { limbs: vec![] }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    #[allow(dead_code)]
+    pub fn mul_small(&self, k: u32) -> BigUint {
+        let mut limbs = Vec::with_capacity(self.limbs.len() + 1);
+        let mut carry = 0u64;
+        for &l in &self.limbs {
+            let x = l as u64 * k as u64 + carry;
+            limbs.push(x as u32);
+            carry = x >> 32;
+        }
+        limbs.push(carry as u32);
+        let mut out = BigUint { limbs };
+        out.trim();
+        out
+    }
+
+    fn trim(&mut self) {
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
+        }
+    }
+}
+
+impl From<u64> for BigUint {
+    fn from(x: u64) -> Self {
+        let mut out = BigUint {
+            limbs: vec![x as u32, (x >> 32) as u32],
+        };
+        out.trim();
+        out
+    }
+}
+
+impl std::ops::Add<&BigUint> for &BigUint {
+    type Output = BigUint;
+
+    fn add(self, other: &BigUint) -> BigUint {
+        let n = self.limbs.len().max(other.limbs.len());
+        let mut limbs = Vec::with_capacity(n + 1);
+        let mut carry = 0u64;
+        for k in 0..n {
+            let a = *self.limbs.get(k).unwrap_or(&0) as u64;
+            let b = *other.limbs.get(k).unwrap_or(&0) as u64;
+            let x = a + b + carry;
+            limbs.push(x as u32);
+            carry = x >> 32;
+        }
+        limbs.push(carry as u32);
+        let mut out = BigUint { limbs };
+        out.trim();
+        out
+    }
+}
+
+impl std::ops::AddAssign<&BigUint> for BigUint {
+    fn add_assign(&mut self, other: &BigUint) {
+        *self = &*self + other;
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.limbs
+            .len()
+            .cmp(&other.limbs.len())
+            .then_with(|| self.limbs.iter().rev().cmp(other.limbs.iter().rev()))
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+        // peel off base 10^9 digits by long division
+        let mut limbs = self.limbs.clone();
+        let mut chunks = vec![];
+        while !limbs.is_empty() {
+            let mut rem = 0u64;
+            for l in limbs.iter_mut().rev() {
+                let x = (rem << 32) | *l as u64;
+                *l = (x / 1_000_000_000) as u32;
+                rem = x % 1_000_000_000;
+            }
+            chunks.push(rem as u32);
+            while limbs.last() == Some(&0) {
+                limbs.pop();
+            }
+        }
+        write!(f, "{}", chunks.pop().unwrap())?;
+        for c in chunks.iter().rev() {
+            write!(f, "{:09}", c)?;
+        }
+        Ok(())
+    }
+}
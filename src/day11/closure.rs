@@ -0,0 +1,227 @@
+use crate::Rules;
+use rayon::prelude::*;
+use std::collections::{HashMap, VecDeque};
+
+// Every stone number reachable from the input under the rules, and what
+// each one turns into
+pub struct Closure {
+    pub stones: Vec<u64>,
+    index: HashMap<u64, usize>,
+    // children[k]: indices of the stones stone k turns into, with repeats
+    children: Vec<Vec<usize>>,
+}
+
+impl Closure {
+    pub fn new(start: &[u64], rules: &Rules) -> Self {
+        let mut closure = Closure {
+            stones: vec![],
+            index: HashMap::new(),
+            children: vec![],
+        };
+        let mut queue = VecDeque::new();
+        for &stone in start {
+            if closure.intern(stone) {
+                queue.push_back(stone);
+            }
+        }
+        while let Some(stone) = queue.pop_front() {
+            let kids: Vec<usize> = rules
+                .apply(stone)
+                .into_iter()
+                .map(|child| {
+                    if closure.intern(child) {
+                        queue.push_back(child);
+                    }
+                    closure.index[&child]
+                })
+                .collect();
+            closure.children[closure.index[&stone]] = kids;
+        }
+        closure
+    }
+
+    // Whether the stone is new
+    fn intern(&mut self, stone: u64) -> bool {
+        if self.index.contains_key(&stone) {
+            return false;
+        }
+        self.index.insert(stone, self.stones.len());
+        self.stones.push(stone);
+        self.children.push(vec![]);
+        true
+    }
+
+    pub fn transitions(&self) -> usize {
+        self.children.iter().map(|c| c.len()).sum()
+    }
+
+    // Stones that can turn back into themselves. The rest only turn up
+    // in the first few blinks.
+    pub fn recurrent(&self) -> Vec<u64> {
+        (0..self.stones.len())
+            .filter(|&k| {
+                let mut seen = vec![false; self.stones.len()];
+                let mut stack = self.children[k].clone();
+                while let Some(s) = stack.pop() {
+                    if s == k {
+                        return true;
+                    }
+                    if !seen[s] {
+                        seen[s] = true;
+                        stack.extend(&self.children[s]);
+                    }
+                }
+                false
+            })
+            .map(|k| self.stones[k])
+            .collect()
+    }
+
+    // The total number of stones after 0, 1, .. len-1 blinks, mod p,
+    // blinking the counts one stone at a time
+    fn totals(&self, start: &[u64], len: usize, p: u64) -> Vec<u64> {
+        let mut counts = vec![0u64; self.stones.len()];
+        for stone in start {
+            let k = self.index[stone];
+            counts[k] = (counts[k] + 1) % p;
+        }
+        let mut totals = Vec::with_capacity(len);
+        for _ in 0..len {
+            totals.push(counts.iter().fold(0, |s, &c| (s + c) % p));
+            let mut next = vec![0u64; counts.len()];
+            for (from, kids) in self.children.iter().enumerate() {
+                for &to in kids {
+                    next[to] = (next[to] + counts[from]) % p;
+                }
+            }
+            counts = next;
+        }
+        totals
+    }
+
+    // The number of stones after `blinks` blinks, mod p. The totals obey
+    // a linear recurrence of order at most n for n stones in the closure,
+    // which Berlekamp-Massey finds from the first 2n of them; x^blinks
+    // mod its characteristic polynomial then gives the answer as a mix
+    // of those first terms. That's O(n^2 log blinks), whatever the number
+    // of blinks.
+    pub fn count_after(&self, start: &[u64], blinks: u64, p: u64) -> u64 {
+        // Berlekamp-Massey divides mod p, which needs p prime
+        assert!(
+            p < 1 << 32 && is_prime(p),
+            "modulus must be a prime below 2^32"
+        );
+        let len = 2 * self.stones.len();
+        let totals = self.totals(start, blinks.saturating_add(1).min(len as u64) as usize, p);
+        if blinks < totals.len() as u64 {
+            return totals[blinks as usize];
+        }
+        let rec = berlekamp_massey(&totals, p);
+        let r = pow_x(blinks, &rec, p);
+        r.iter()
+            .zip(&totals)
+            .fold(0, |s, (&a, &b)| (s + a * b % p) % p)
+    }
+}
+
+// Trial division, which is quick enough below 2^32
+fn is_prime(p: u64) -> bool {
+    p >= 2 && (2..).take_while(|d| d * d <= p).all(|d| !p.is_multiple_of(d))
+}
+
+fn pow_mod(mut b: u64, mut e: u64, p: u64) -> u64 {
+    let mut out = 1;
+    b %= p;
+    while e > 0 {
+        if e & 1 == 1 {
+            out = out * b % p;
+        }
+        b = b * b % p;
+        e >>= 1;
+    }
+    out
+}
+
+// The shortest recurrence s[t] = rec[0] s[t-1] + rec[1] s[t-2] + ...
+// that the sequence obeys, mod the prime p
+fn berlekamp_massey(s: &[u64], p: u64) -> Vec<u64> {
+    let inv = |x: u64| pow_mod(x, p - 2, p);
+    // connection polynomials, constant term first: the current one and
+    // the one before the last change of length
+    let mut c = vec![1u64];
+    let mut b = vec![1u64];
+    let (mut l, mut m, mut last) = (0, 1, 1u64);
+    for i in 0..s.len() {
+        let d = (1..=l).fold(s[i], |d, j| (d + c[j] * s[i - j]) % p);
+        if d == 0 {
+            m += 1;
+            continue;
+        }
+        let coef = d * inv(last) % p;
+        let prev = c.clone();
+        if c.len() < b.len() + m {
+            c.resize(b.len() + m, 0);
+        }
+        for (j, &x) in b.iter().enumerate() {
+            c[j + m] = (c[j + m] + p - coef * x % p) % p;
+        }
+        if 2 * l <= i {
+            l = i + 1 - l;
+            b = prev;
+            last = d;
+            m = 1;
+        } else {
+            m += 1;
+        }
+    }
+    c.resize(l + 1, 0);
+    c[1..].iter().map(|&x| (p - x) % p).collect()
+}
+
+// a * b mod the recurrence's characteristic polynomial, both of degree
+// less than its order
+fn mul_mod_rec(a: &[u64], b: &[u64], rec: &[u64], p: u64) -> Vec<u64> {
+    let l = rec.len();
+    let mut r: Vec<u64> = (0..2 * l - 1)
+        .into_par_iter()
+        .map(|k| {
+            let lo = k.saturating_sub(l - 1);
+            let s: u128 = (lo..=k.min(l - 1)).map(|i| (a[i] * b[k - i]) as u128).sum();
+            (s % p as u128) as u64
+        })
+        .collect();
+    // x^l = rec[0] x^(l-1) + rec[1] x^(l-2) + ..., from the top down
+    for i in (l..2 * l - 1).rev() {
+        let x = r[i];
+        if x == 0 {
+            continue;
+        }
+        for (j, &c) in rec.iter().enumerate() {
+            r[i - 1 - j] = (r[i - 1 - j] + x * c) % p;
+        }
+    }
+    r.truncate(l);
+    r
+}
+
+// x^e mod the recurrence's characteristic polynomial, by squaring
+fn pow_x(e: u64, rec: &[u64], p: u64) -> Vec<u64> {
+    let l = rec.len();
+    let mut out = vec![0; l];
+    if l == 0 {
+        return out;
+    }
+    out[0] = 1;
+    for bit in (0..64 - e.leading_zeros()).rev() {
+        out = mul_mod_rec(&out, &out, rec, p);
+        if e >> bit & 1 == 1 {
+            // times x: shift up, folding the top coefficient back down
+            let top = out.pop().unwrap();
+            out.insert(0, 0);
+            for (k, &c) in rec.iter().enumerate() {
+                out[l - 1 - k] = (out[l - 1 - k] + top * c) % p;
+            }
+        }
+    }
+    out
+}
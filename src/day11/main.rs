@@ -1,43 +1,204 @@
-use std::{collections::HashMap, fs};
+#[path = "../common/bignum.rs"]
+mod bignum;
+mod closure;
 
-fn count(stone: i64, rounds_left: i64, memo: &mut HashMap<[i64; 2], i64>) -> i64 {
-    // println!("stone: {}, rounds_left: {}", stone, rounds_left);
-    if rounds_left == 0 {
-        return 1;
+use bignum::BigUint;
+use closure::Closure;
+use std::{collections::HashMap, env, fmt, fs};
+
+// One rule for what a stone turns into on a blink
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rule {
+    // a stone engraved `from` becomes `to`
+    Replace { from: u64, to: u64 },
+    // a stone with an even number of digits splits into its left and
+    // right halves
+    SplitEvenDigits,
+    // any stone is multiplied by this
+    Multiply(u64),
+}
+
+impl Rule {
+    // What the stone turns into, or None if the rule doesn't apply
+    fn apply(&self, stone: u64) -> Option<Vec<u64>> {
+        match *self {
+            Rule::Replace { from, to } => (stone == from).then(|| vec![to]),
+            Rule::SplitEvenDigits => {
+                if stone == 0 {
+                    return None;
+                }
+                let digits = stone.ilog10() + 1;
+                if !digits.is_multiple_of(2) {
+                    return None;
+                }
+                let base = 10u64.pow(digits / 2);
+                Some(vec![stone / base, stone % base])
+            }
+            Rule::Multiply(k) => Some(vec![stone
+                .checked_mul(k)
+                .unwrap_or_else(|| panic!("stone {} * {} overflows u64", stone, k))]),
+        }
+    }
+}
+
+// The first rule that applies wins
+#[derive(Debug, Clone)]
+struct Rules(Vec<Rule>);
+
+impl Rules {
+    fn standard() -> Self {
+        Rules(vec![
+            Rule::Replace { from: 0, to: 1 },
+            Rule::SplitEvenDigits,
+            Rule::Multiply(2024),
+        ])
     }
 
-    if let Some(&result) = memo.get(&[stone, rounds_left]) {
-        return result;
+    fn apply(&self, stone: u64) -> Vec<u64> {
+        self.0
+            .iter()
+            .find_map(|rule| rule.apply(stone))
+            .unwrap_or_else(|| panic!("no rule for stone {}", stone))
     }
+}
 
-    let res = if stone == 0 {
-        count(1, rounds_left - 1, memo)
-    } else {
-        let digits = stone.ilog(10) as i64 + 1;
+// Stone counts: u128, which runs out after a couple hundred blinks, or
+// BigUint for anything longer
+trait Count: Clone + fmt::Display {
+    fn from_u64(x: u64) -> Self;
+    fn add_to(&mut self, other: &Self);
+}
 
-        if digits % 2 == 0 {
-            let base = 10i64.pow((digits / 2) as u32);
-            let lhs = stone / base;
-            let rhs = stone % base;
+impl Count for u128 {
+    fn from_u64(x: u64) -> Self {
+        x as u128
+    }
 
-            count(lhs, rounds_left - 1, memo) + count(rhs, rounds_left - 1, memo)
-        } else {
-            count(stone * 2024, rounds_left - 1, memo)
+    fn add_to(&mut self, other: &Self) {
+        *self = self
+            .checked_add(*other)
+            .expect("stone count overflows u128");
+    }
+}
+
+impl Count for BigUint {
+    fn from_u64(x: u64) -> Self {
+        BigUint::from(x)
+    }
+
+    fn add_to(&mut self, other: &Self) {
+        *self += other;
+    }
+}
+
+// How many stones are engraved with each number. The order of the stones
+// never matters for the rules, so this is all there is to track.
+#[derive(Debug, Clone)]
+struct Stones<C> {
+    counts: HashMap<u64, C>,
+}
+
+impl<C: Count> Stones<C> {
+    fn from_str(txt: &str) -> Self {
+        let mut counts: HashMap<u64, C> = HashMap::new();
+        for w in txt.split_ascii_whitespace() {
+            let stone = w.parse().unwrap();
+            counts
+                .entry(stone)
+                .and_modify(|c| c.add_to(&C::from_u64(1)))
+                .or_insert(C::from_u64(1));
         }
-    };
+        Stones { counts }
+    }
 
-    memo.insert([stone, rounds_left], res);
-    res
+    fn blink(&mut self, rules: &Rules) {
+        let mut next: HashMap<u64, C> = HashMap::new();
+        for (&stone, count) in &self.counts {
+            for child in rules.apply(stone) {
+                match next.get_mut(&child) {
+                    Some(c) => c.add_to(count),
+                    None => {
+                        next.insert(child, count.clone());
+                    }
+                }
+            }
+        }
+        self.counts = next;
+    }
+
+    fn total(&self) -> C {
+        let mut total = C::from_u64(0);
+        for c in self.counts.values() {
+            total.add_to(c);
+        }
+        total
+    }
+
+    fn distinct(&self) -> usize {
+        self.counts.len()
+    }
+}
+
+// (distinct stones, total stones) after each blink 1..=blinks
+fn evolve<C: Count>(txt: &str, rules: &Rules, blinks: usize) -> Vec<(usize, C)> {
+    let mut stones: Stones<C> = Stones::from_str(txt);
+    (0..blinks)
+        .map(|_| {
+            stones.blink(rules);
+            (stones.distinct(), stones.total())
+        })
+        .collect()
+}
+
+fn count_after(txt: &str, blinks: usize) -> u128 {
+    let mut stones: Stones<u128> = Stones::from_str(txt);
+    let rules = Rules::standard();
+    for _ in 0..blinks {
+        stones.blink(&rules);
+    }
+    stones.total()
 }
 
-fn part1(txt: &str) -> i64 {
-    txt.split_ascii_whitespace()
-        .map(|w| count(w.parse::<i64>().unwrap(), 75, &mut HashMap::new()))
-        .sum()
+fn part1(txt: &str) -> u128 {
+    count_after(txt, 25)
 }
 
-fn part2(txt: &str) -> i64 {
-    0
+fn part2(txt: &str) -> u128 {
+    count_after(txt, 75)
+}
+
+fn print_blinks<C: Count>(txt: &str, blinks: usize) {
+    for (k, (distinct, total)) in evolve::<C>(txt, &Rules::standard(), blinks)
+        .iter()
+        .enumerate()
+    {
+        println!(
+            "blink {:>4}: {:>5} distinct, {} stones",
+            k + 1,
+            distinct,
+            total
+        );
+    }
+}
+
+fn print_closure(txt: &str, blinks: u64, p: u64) {
+    let start: Vec<u64> = txt
+        .split_ascii_whitespace()
+        .map(|w| w.parse().unwrap())
+        .collect();
+    let closure = Closure::new(&start, &Rules::standard());
+    println!(
+        "{} stones reachable, {} transitions, {} of them recurrent",
+        closure.stones.len(),
+        closure.transitions(),
+        closure.recurrent().len()
+    );
+    println!(
+        "Stones after {} blinks, mod {}: {}",
+        blinks,
+        p,
+        closure.count_after(&start, blinks, p)
+    );
 }
 
 fn main() {
@@ -49,7 +210,67 @@ fn main() {
     // let path = String::from(root) + "/src/" + day_x + "/test2.txt";
     let txt = fs::read_to_string(path).unwrap();
 
+    // `cargo run --bin day11 -- blinks N [big]`
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(|a| a.as_str()) == Some("blinks") {
+        let blinks = args[2].parse().unwrap();
+        if args.get(3).map(|a| a.as_str()) == Some("big") {
+            print_blinks::<BigUint>(&txt, blinks);
+        } else {
+            print_blinks::<u128>(&txt, blinks);
+        }
+        return;
+    }
+
+    // `cargo run --bin day11 -- closure N PRIME`
+    if args.get(1).map(|a| a.as_str()) == Some("closure") {
+        print_closure(&txt, args[2].parse().unwrap(), args[3].parse().unwrap());
+        return;
+    }
+
     println!("This is {}", day_x);
     println!("Part 1: {:?}", part1(&txt));
     println!("Part 2: {:?}", part2(&txt));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a decimal count mod p
+    fn modulo(count: &BigUint, p: u64) -> u64 {
+        count
+            .to_string()
+            .bytes()
+            .fold(0, |r, d| (r * 10 + (d - b'0') as u64) % p)
+    }
+
+    #[test]
+    fn closure_count_matches_evolve() {
+        let txt = "125 17";
+        let start = [125, 17];
+        let closure = Closure::new(&start, &Rules::standard());
+        let totals = evolve::<BigUint>(txt, &Rules::standard(), 400);
+        // past 2n blinks, so the recurrence is used and not just the
+        // first terms
+        assert!(2 * closure.stones.len() < 400);
+        for p in [2, 65537, 998244353, 1000000007, 4294967291] {
+            for blinks in [1, 6, 25, 75, 151, 152, 153, 299, 400] {
+                assert_eq!(
+                    closure.count_after(&start, blinks, p),
+                    modulo(&totals[blinks as usize - 1].1, p),
+                    "{} blinks mod {}",
+                    blinks,
+                    p
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "modulus must be a prime")]
+    fn closure_count_rejects_composite_modulus() {
+        let closure = Closure::new(&[125, 17], &Rules::standard());
+        closure.count_after(&[125, 17], 9000, 1000);
+    }
+}
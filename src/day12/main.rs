@@ -1,4 +1,4 @@
-use std::{collections::HashSet, fs};
+use std::{collections::HashMap, env, fs, fmt::Write};
 
 #[allow(non_snake_case)]
 struct Grid {
@@ -7,6 +7,58 @@ struct Grid {
     M: isize,
 }
 
+// A connected patch of one plant. Cells are [i, j], sorted.
+#[derive(Debug, Clone)]
+struct Region {
+    plant: char,
+    cells: Vec<[isize; 2]>,
+    perimeter: isize,
+    sides: isize,
+    // bounding box corners, inclusive
+    min: [isize; 2],
+    max: [isize; 2],
+    // enclosed pockets of other plants, and the regions inside them
+    holes: usize,
+    inner: Vec<usize>,
+}
+
+impl Region {
+    fn area(&self) -> isize {
+        self.cells.len() as isize
+    }
+}
+
+// A region's fence as closed loops of corner points [x, y] = [j, i],
+// keeping only the corners where the fence turns. Walking a loop, the
+// region is always on the right, so on screen (y down) the outer loop
+// goes clockwise and the holes counterclockwise.
+#[derive(Debug, Clone)]
+struct Outline {
+    outer: Vec<[isize; 2]>,
+    holes: Vec<Vec<[isize; 2]>>,
+}
+
+impl Outline {
+    fn vertices(&self) -> usize {
+        self.outer.len() + self.holes.iter().map(|h| h.len()).sum::<usize>()
+    }
+
+    fn loops(&self) -> impl Iterator<Item = &Vec<[isize; 2]>> {
+        std::iter::once(&self.outer).chain(self.holes.iter())
+    }
+}
+
+// Twice the signed area of a loop, positive for clockwise on screen
+fn signed_area(points: &[[isize; 2]]) -> isize {
+    (0..points.len())
+        .map(|k| {
+            let [x0, y0] = points[k];
+            let [x1, y1] = points[(k + 1) % points.len()];
+            x0 * y1 - x1 * y0
+        })
+        .sum()
+}
+
 impl Grid {
     fn from_str(txt: &str) -> Self {
         let grid = txt.chars().filter(|&c| c != '\n').collect();
@@ -23,88 +75,336 @@ impl Grid {
         }
     }
 
-    fn flood(
-        &self,
-        i: isize,
-        j: isize,
-        seen: &mut HashSet<[isize; 2]>,
-        discount: bool,
-    ) -> Option<[isize; 2]> {
-        if seen.contains(&[i, j]) {
-            return None;
-        }
-        if let Some(c) = self.get(i, j) {
-            seen.insert([i, j]);
-            let mut area = 1;
-
-            let mut perimeter = 4;
-            for [di, dj] in [[-1isize, 0], [1, 0], [0, -1], [0, 1]] {
-                let mut lostedge = false;
-                if let Some(d) = self.get(i + di, j + dj) {
-                    if d == c {
-                        perimeter -= 1;
-                        lostedge = true;
-                        if let Some(ap) = self.flood(i + di, j + dj, seen, discount) {
-                            area += ap[0];
-                            perimeter += ap[1];
-                        }
-                    }
-                }
-                if discount && !lostedge {
-                    // Part 2
-                    // If this square is the start of a straight edge, count the side.
-                    // This means we need to decrement perimeter if we are a continuation.
-
-                    // first, look one square over 90 degrees counterclockwise
-                    let dd = if dj == 0 { [0, di] } else { [-dj, 0] };
-
-                    if let Some(e) = self.get(i + dd[0], j + dd[1]) {
-                        // if that square matches ours, we might need to lose the edge. keep checking.
-                        if e == c {
-                            // now, check one more square over, in the direction of the edge
-                            if let Some(f) = self.get(i + dd[0] + di, j + dd[1] + dj) {
-                                // if that square does not match ours, then we know that our neighbor
-                                // already has an edge going. lose our edge.
-                                if c != f {
-                                    perimeter -= 1;
-                                }
-                            } else {
-                                // edge of the board
+    // How many of the fence edges around (i, j) count: all of them for
+    // the perimeter, or with `discount` only those that start a side.
+    fn edges(&self, i: isize, j: isize, discount: bool) -> isize {
+        let c = self.get(i, j).unwrap();
+        let mut perimeter = 4;
+        for [di, dj] in [[-1isize, 0], [1, 0], [0, -1], [0, 1]] {
+            if self.get(i + di, j + dj) == Some(c) {
+                perimeter -= 1;
+            } else if discount {
+                // Part 2
+                // If this square is the start of a straight edge, count the side.
+                // This means we need to decrement perimeter if we are a continuation.
+
+                // first, look one square over 90 degrees counterclockwise
+                let dd = if dj == 0 { [0, di] } else { [-dj, 0] };
+
+                if let Some(e) = self.get(i + dd[0], j + dd[1]) {
+                    // if that square matches ours, we might need to lose the edge. keep checking.
+                    if e == c {
+                        // now, check one more square over, in the direction of the edge
+                        if let Some(f) = self.get(i + dd[0] + di, j + dd[1] + dj) {
+                            // if that square does not match ours, then we know that our neighbor
+                            // already has an edge going. lose our edge.
+                            if c != f {
                                 perimeter -= 1;
                             }
+                        } else {
+                            // edge of the board
+                            perimeter -= 1;
                         }
                     }
                 }
             }
-            return Some([area, perimeter]);
-        } else {
-            return None;
         }
+        perimeter
     }
 
-    fn flood_all(&self, discount: bool) -> isize {
-        let mut seen = HashSet::new();
-        let mut areas = Vec::new();
+    // Every region in reading order of their first cell, along with the
+    // region number of each cell
+    fn regions(&self) -> (Vec<Region>, Vec<usize>) {
+        let mut region_of = vec![usize::MAX; self.grid.len()];
+        let mut regions = vec![];
         for i in 0..self.N {
             for j in 0..self.M {
-                if let Some(ap) = self.flood(i, j, &mut seen, discount) {
-                    // println!("Region {}: area {}, perimeter {}", self.get(i, j).unwrap(), ap[0], ap[1]);
-                    areas.push(ap);
+                if region_of[(i * self.M + j) as usize] != usize::MAX {
+                    continue;
+                }
+                let id = regions.len();
+                let plant = self.get(i, j).unwrap();
+                let mut region = Region {
+                    plant,
+                    cells: vec![],
+                    perimeter: 0,
+                    sides: 0,
+                    min: [i, j],
+                    max: [i, j],
+                    holes: 0,
+                    inner: vec![],
+                };
+
+                // iterative flood fill
+                region_of[(i * self.M + j) as usize] = id;
+                let mut stack = vec![[i, j]];
+                while let Some([i, j]) = stack.pop() {
+                    region.cells.push([i, j]);
+                    region.perimeter += self.edges(i, j, false);
+                    region.sides += self.edges(i, j, true);
+                    region.min = [region.min[0].min(i), region.min[1].min(j)];
+                    region.max = [region.max[0].max(i), region.max[1].max(j)];
+                    for [di, dj] in [[-1isize, 0], [1, 0], [0, -1], [0, 1]] {
+                        let (ni, nj) = (i + di, j + dj);
+                        if self.get(ni, nj) == Some(plant)
+                            && region_of[(ni * self.M + nj) as usize] == usize::MAX
+                        {
+                            region_of[(ni * self.M + nj) as usize] = id;
+                            stack.push([ni, nj]);
+                        }
+                    }
+                }
+                region.cells.sort();
+                regions.push(region);
+            }
+        }
+
+        let holes: Vec<(usize, Vec<usize>)> = regions
+            .iter()
+            .enumerate()
+            .map(|(id, region)| self.holes(region, id, &region_of))
+            .collect();
+        for (region, (holes, inner)) in regions.iter_mut().zip(holes) {
+            region.holes = holes;
+            region.inner = inner;
+        }
+        (regions, region_of)
+    }
+
+    // Flood what's not in the region, within its bounding box plus a
+    // margin. Anything not connected to the margin is in a hole.
+    fn holes(&self, region: &Region, id: usize, region_of: &[usize]) -> (usize, Vec<usize>) {
+        let [i0, j0] = [region.min[0] - 1, region.min[1] - 1];
+        let [i1, j1] = [region.max[0] + 1, region.max[1] + 1];
+        let w = j1 - j0 + 1;
+        let idx = |i: isize, j: isize| ((i - i0) * w + (j - j0)) as usize;
+        let inside = |i: isize, j: isize| {
+            self.get(i, j).is_some() && region_of[(i * self.M + j) as usize] == id
+        };
+
+        let mut seen = vec![false; ((i1 - i0 + 1) * w) as usize];
+        let mut holes = 0;
+        let mut inner = vec![];
+        for i in i0..=i1 {
+            for j in j0..=j1 {
+                if seen[idx(i, j)] || inside(i, j) {
+                    continue;
+                }
+                let mut touches_margin = false;
+                let mut found = vec![];
+                seen[idx(i, j)] = true;
+                let mut stack = vec![[i, j]];
+                while let Some([i, j]) = stack.pop() {
+                    if i == i0 || i == i1 || j == j0 || j == j1 {
+                        touches_margin = true;
+                    } else {
+                        found.push(region_of[(i * self.M + j) as usize]);
+                    }
+                    for [di, dj] in [[-1isize, 0], [1, 0], [0, -1], [0, 1]] {
+                        let (ni, nj) = (i + di, j + dj);
+                        if ni < i0 || ni > i1 || nj < j0 || nj > j1 {
+                            continue;
+                        }
+                        if !seen[idx(ni, nj)] && !inside(ni, nj) {
+                            seen[idx(ni, nj)] = true;
+                            stack.push([ni, nj]);
+                        }
+                    }
+                }
+                if !touches_margin {
+                    holes += 1;
+                    inner.extend(found);
+                }
+            }
+        }
+        inner.sort();
+        inner.dedup();
+        (holes, inner)
+    }
+
+    fn outline(&self, id: usize, region: &Region, region_of: &[usize]) -> Outline {
+        let inside = |i: isize, j: isize| {
+            self.get(i, j).is_some() && region_of[(i * self.M + j) as usize] == id
+        };
+
+        // unit fence edges as corner [x, y] -> direction, with the cell
+        // on their right
+        let mut edges: HashMap<[isize; 2], Vec<[isize; 2]>> = HashMap::new();
+        for &[i, j] in &region.cells {
+            let (x, y) = (j, i);
+            for (out, start, dir) in [
+                (!inside(i - 1, j), [x, y], [1, 0]),
+                (!inside(i, j + 1), [x + 1, y], [0, 1]),
+                (!inside(i + 1, j), [x + 1, y + 1], [-1, 0]),
+                (!inside(i, j - 1), [x, y + 1], [0, -1]),
+            ] {
+                if out {
+                    edges.entry(start).or_default().push(dir);
                 }
             }
         }
-        areas.iter().map(|[a, p]| a * p).sum()
+
+        let mut loops = vec![];
+        while let Some(&start) = edges.keys().min_by_key(|&&[x, y]| (y, x)) {
+            let first = edges[&start][0];
+            let mut corners = vec![];
+            let (mut p, mut dir) = (start, first);
+            loop {
+                let next = [p[0] + dir[0], p[1] + dir[1]];
+                let outs = edges.get(&next).map(|v| v.as_slice()).unwrap_or(&[]);
+                // where two loops touch at a corner, turn left (away from
+                // the region) so each pocket outside gets a loop of its own
+                let left = [dir[1], -dir[0]];
+                let right = [-dir[1], dir[0]];
+                let turn = [left, dir, right]
+                    .into_iter()
+                    .find(|d| outs.contains(d))
+                    .expect("fence loop is not closed");
+                if turn != dir {
+                    corners.push(next);
+                }
+                let remaining = edges.get_mut(&next).unwrap();
+                remaining.retain(|d| *d != turn);
+                if remaining.is_empty() {
+                    edges.remove(&next);
+                }
+                if next == start && turn == first {
+                    break;
+                }
+                (p, dir) = (next, turn);
+            }
+            loops.push(corners);
+        }
+
+        let (outer, holes): (Vec<_>, Vec<_>) = loops.into_iter().partition(|l| signed_area(l) > 0);
+        assert_eq!(outer.len(), 1, "region has {} outer loops", outer.len());
+        Outline {
+            outer: outer.into_iter().next().unwrap(),
+            holes,
+        }
+    }
+
+    fn price(&self, discount: bool) -> isize {
+        let (regions, _) = self.regions();
+        regions
+            .iter()
+            .map(|r| {
+                let fence = if discount { r.sides } else { r.perimeter };
+                r.area() * fence
+            })
+            .sum()
+    }
+}
+
+fn print_regions(grid: &Grid) {
+    let (regions, _) = grid.regions();
+    for (id, r) in regions.iter().enumerate() {
+        println!(
+            "region {:>4} '{}': area {}, perimeter {}, sides {}, rows {}..={}, cols {}..={}, {} holes with regions {:?}",
+            id,
+            r.plant,
+            r.area(),
+            r.perimeter,
+            r.sides,
+            r.min[0],
+            r.max[0],
+            r.min[1],
+            r.max[1],
+            r.holes,
+            r.inner
+        );
+    }
+}
+
+fn region_outlines(grid: &Grid) -> Vec<(Region, Outline)> {
+    let (regions, region_of) = grid.regions();
+    regions
+        .into_iter()
+        .enumerate()
+        .map(|(id, r)| {
+            let outline = grid.outline(id, &r, &region_of);
+            (r, outline)
+        })
+        .collect()
+}
+
+fn svg(grid: &Grid) -> String {
+    let scale = 10;
+    let mut out = String::new();
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}">"#,
+        grid.M * scale,
+        grid.N * scale
+    )
+    .unwrap();
+    for (r, outline) in region_outlines(grid) {
+        let mut d = String::new();
+        for points in outline.loops() {
+            for (k, [x, y]) in points.iter().enumerate() {
+                let cmd = if k == 0 { 'M' } else { 'L' };
+                write!(d, "{}{} {} ", cmd, x * scale, y * scale).unwrap();
+            }
+            d.push('Z');
+        }
+        let hue = (r.plant as u32 * 47) % 360;
+        writeln!(
+            out,
+            r#"  <path d="{}" fill="hsl({}, 60%, 70%)" fill-rule="evenodd" stroke="black"><title>{}</title></path>"#,
+            d, hue, r.plant
+        )
+        .unwrap();
     }
+    out.push_str("</svg>\n");
+    out
+}
+
+// GeoJSON-like: a FeatureCollection of Polygons, each ring closed and
+// in [x, y] = [column, row] corner coordinates
+fn geojson(grid: &Grid) -> String {
+    let features: Vec<String> = region_outlines(grid)
+        .iter()
+        .map(|(r, outline)| {
+            let rings: Vec<String> = outline
+                .loops()
+                .map(|points| {
+                    let coords: Vec<String> = points
+                        .iter()
+                        .chain(points.first())
+                        .map(|[x, y]| format!("[{}, {}]", x, y))
+                        .collect();
+                    format!("[{}]", coords.join(", "))
+                })
+                .collect();
+            format!(
+                r#"    {{"type": "Feature", "properties": {{"plant": "{}", "area": {}, "perimeter": {}, "sides": {}}}, "geometry": {{"type": "Polygon", "coordinates": [{}]}}}}"#,
+                r.plant,
+                r.area(),
+                r.perimeter,
+                r.sides,
+                rings.join(", ")
+            )
+        })
+        .collect();
+    format!(
+        "{{\"type\": \"FeatureCollection\", \"features\": [\n{}\n]}}\n",
+        features.join(",\n")
+    )
 }
 
 fn part1(txt: &str) -> isize {
     let grid = Grid::from_str(txt);
-    grid.flood_all(false)
+    grid.price(false)
 }
 
 fn part2(txt: &str) -> isize {
     let grid = Grid::from_str(txt);
-    grid.flood_all(true)
+    // the sides counted with `discount` are the corners of the outline
+    for (r, outline) in region_outlines(&grid) {
+        assert_eq!(outline.vertices() as isize, r.sides, "region {:?}", r.min);
+        assert_eq!(outline.holes.len(), r.holes, "region {:?}", r.min);
+    }
+    grid.price(true)
 }
 
 fn main() {
@@ -119,6 +419,22 @@ fn main() {
     // let path = String::from(root) + "/src/" + day_x + "/test5.txt";
     let txt = fs::read_to_string(path).unwrap();
 
+    // `cargo run --bin day12 -- regions`
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(|a| a.as_str()) == Some("regions") {
+        print_regions(&Grid::from_str(&txt));
+        return;
+    }
+    // `cargo run --bin day12 -- svg` / `-- geojson`
+    if args.get(1).map(|a| a.as_str()) == Some("svg") {
+        print!("{}", svg(&Grid::from_str(&txt)));
+        return;
+    }
+    if args.get(1).map(|a| a.as_str()) == Some("geojson") {
+        print!("{}", geojson(&Grid::from_str(&txt)));
+        return;
+    }
+
     println!("This is {}", day_x);
     println!("Part 1: {:?}", part1(&txt));
     println!("Part 2: {:?}", part2(&txt));
@@ -1,11 +1,10 @@
 use regex::Regex;
-use std::fs;
+use std::{env, fs};
 
-#[allow(non_snake_case)]
 #[derive(Debug)]
 struct Machine {
     prize: Point,
-    matrix: Matrix,
+    buttons: Vec<Point>,
 }
 
 #[derive(Debug)]
@@ -19,10 +18,48 @@ struct Point {
     j: isize,
 }
 
+// The cheapest way to win a prize: presses[k] presses of button k
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct Solution {
+    tokens: i128,
+    presses: Vec<i128>,
+}
+
+#[derive(Debug, Clone)]
+struct Params {
+    // tokens per press of each button by label, A first
+    tokens: Vec<i128>,
+    // most presses of any one button
+    max_presses: Option<i128>,
+    // added to every prize position
+    offset: Point,
+}
+
+impl Params {
+    fn part1() -> Self {
+        Params {
+            tokens: vec![3, 1],
+            max_presses: Some(100),
+            offset: Point { i: 0, j: 0 },
+        }
+    }
+
+    fn part2() -> Self {
+        Params {
+            tokens: vec![3, 1],
+            max_presses: None,
+            offset: Point {
+                i: 10000000000000,
+                j: 10000000000000,
+            },
+        }
+    }
+}
+
 #[derive(Debug)]
 struct Arcade {
     machines: Vec<Machine>,
-    part2: bool,
+    params: Params,
 }
 
 impl Point {
@@ -45,121 +82,446 @@ impl std::ops::Add<Point> for Point {
     }
 }
 
-impl std::ops::Div<isize> for Point {
+impl std::ops::Sub<Point> for Point {
     type Output = Point;
 
-    fn div(self, rhs: isize) -> Point {
+    fn sub(self, rhs: Point) -> Point {
         Point {
-            i: self.i / rhs,
-            j: self.j / rhs,
+            i: self.i - rhs.i,
+            j: self.j - rhs.j,
         }
     }
 }
 
-impl std::ops::Rem<isize> for Point {
+impl std::ops::Mul<isize> for Point {
     type Output = Point;
 
-    fn rem(self, rhs: isize) -> Point {
+    fn mul(self, rhs: isize) -> Point {
         Point {
-            i: self.i % rhs,
-            j: self.j % rhs,
+            i: self.i * rhs,
+            j: self.j * rhs,
         }
     }
 }
 
 #[allow(non_snake_case)]
 impl Machine {
-    fn from_str(txt: &str, part2: bool) -> Self {
+    // Any number of buttons, then the prize
+    fn from_str(txt: &str, offset: Point) -> Self {
         let re = Regex::new(r"X[+=](\d+), Y[+=](\d+)").unwrap();
-        let mut caps = re.captures_iter(txt).map(|c| {
-            [
-                c[1].parse::<isize>().unwrap(),
-                c[2].parse::<isize>().unwrap(),
-            ]
-        });
-
-        let off = if part2 {
-            Point {
-                i: 10000000000000,
-                j: 10000000000000,
-            }
-        } else {
-            Point { i: 0, j: 0 }
-        };
-        let A = Point::from_array(caps.next().unwrap());
-        let B = Point::from_array(caps.next().unwrap());
-        let prize = Point::from_array(caps.next().unwrap()) + off;
+        let mut points: Vec<Point> = re
+            .captures_iter(txt)
+            .map(|c| {
+                Point::from_array([
+                    c[1].parse::<isize>().unwrap(),
+                    c[2].parse::<isize>().unwrap(),
+                ])
+            })
+            .collect();
+
+        let prize = points.pop().unwrap() + offset;
+        Machine {
+            prize,
+            buttons: points,
+        }
+    }
+
+    // The cheapest way to the prize. Button k costs params.tokens[k], so
+    // machines with fewer buttons just use the first few costs.
+    //
+    // Two buttons with independent directions are solved exactly, along
+    // with one more "free" button whose presses are solved as a
+    // progression (see `solve_free`). Any further buttons are searched,
+    // bounded by the press limit or by how far each can go before
+    // overshooting the prize, and a search too big to finish is refused.
+    fn solve(&self, params: &Params) -> Option<Solution> {
+        let n = self.buttons.len();
+        assert!(n >= 2, "need at least two buttons");
+
+        // put a pair with independent directions last, if there is one
+        let det = |p: Point, q: Point| p.i as i128 * q.j as i128 - q.i as i128 * p.j as i128;
+        let mut order: Vec<usize> = (0..n).collect();
+        if let Some((p, q)) = (0..n)
+            .rev()
+            .flat_map(|q| (0..q).rev().map(move |p| (p, q)))
+            .find(|&(p, q)| det(self.buttons[p], self.buttons[q]) != 0)
+        {
+            order.retain(|&k| k != p && k != q);
+            order.extend([p, q]);
+        }
+        let buttons: Vec<Point> = order.iter().map(|&k| self.buttons[k]).collect();
+        let tokens: Vec<i128> = order.iter().map(|&k| params.tokens[k]).collect();
+        let free = n > 2 && det(buttons[n - 2], buttons[n - 1]) != 0;
 
-        let matrix = Matrix {
-            a: [[A.i, B.i], [A.j, B.j]],
+        let search = Search {
+            buttons: &buttons,
+            tokens: &tokens,
+            max_presses: params.max_presses,
+            solved: if free { n - 3 } else { n - 2 },
         };
+        let size = buttons[..search.solved]
+            .iter()
+            .map(|&b| search.limit(b, self.prize).saturating_add(1))
+            .fold(1i128, |acc, m| acc.saturating_mul(m));
+        assert!(
+            size <= MAX_SEARCH,
+            "the prize at {},{} needs a search over up to {} combinations of extra button presses; set a press limit",
+            self.prize.i,
+            self.prize.j,
+            size
+        );
+
+        let mut best: Option<Solution> = None;
+        let mut presses = vec![0; n];
+        search.run(0, self.prize, 0, &mut presses, &mut best);
 
-        Machine { prize, matrix }
+        // back to the machine's own button order
+        best.map(|sol| {
+            let mut presses = vec![0; n];
+            for (&k, &m) in order.iter().zip(&sol.presses) {
+                presses[k] = m;
+            }
+            Solution {
+                tokens: sol.tokens,
+                presses,
+            }
+        })
     }
+}
 
-    fn tokens_to_solve(&self) -> isize {
-        if let Some(sol) = self.matrix.solve(&self.prize) {
-            3 * sol.i + sol.j
-        } else {
-            0
+// Most combinations of presses `Machine::solve` will search through
+const MAX_SEARCH: i128 = 10_000_000;
+
+// Depth-first search over the presses of buttons[..solved]; the rest are
+// solved directly
+struct Search<'a> {
+    buttons: &'a [Point],
+    tokens: &'a [i128],
+    max_presses: Option<i128>,
+    solved: usize,
+}
+
+#[allow(non_snake_case)]
+impl Search<'_> {
+    // how often button can be pressed without overshooting `rest`
+    fn limit(&self, button: Point, rest: Point) -> i128 {
+        if button == (Point { i: 0, j: 0 }) {
+            return 0;
+        }
+        let mut limit = self.max_presses.unwrap_or(i128::MAX);
+        // button deltas are never negative
+        for (d, r) in [(button.i, rest.i), (button.j, rest.j)] {
+            if d > 0 {
+                limit = limit.min((r / d) as i128);
+            }
+        }
+        limit
+    }
+
+    fn run(
+        &self,
+        k: usize,
+        rest: Point,
+        tokens: i128,
+        presses: &mut Vec<i128>,
+        best: &mut Option<Solution>,
+    ) {
+        // token costs are non-negative, so this can only get worse
+        if best.as_ref().is_some_and(|b| tokens >= b.tokens) {
+            return;
+        }
+        let n = self.buttons.len();
+        if k == self.solved {
+            let (A, B) = (self.buttons[n - 2], self.buttons[n - 1]);
+            let costs = [self.tokens[n - 2], self.tokens[n - 1]];
+            let found = if k + 3 == n {
+                let C = self.buttons[k];
+                self.solve_free(C, A, B, rest, [self.tokens[k], costs[0], costs[1]])
+                    .map(|(m, a, b)| vec![m, a, b])
+            } else {
+                let matrix = Matrix {
+                    a: [[A.i, B.i], [A.j, B.j]],
+                };
+                matrix
+                    .solve(&rest, costs, self.max_presses)
+                    .map(|(a, b)| vec![a, b])
+            };
+            if let Some(found) = found {
+                let total = tokens
+                    + found
+                        .iter()
+                        .zip(&self.tokens[k..])
+                        .map(|(m, t)| m * t)
+                        .sum::<i128>();
+                if best.as_ref().is_none_or(|b| total < b.tokens) {
+                    presses[k..].copy_from_slice(&found);
+                    *best = Some(Solution {
+                        tokens: total,
+                        presses: presses.clone(),
+                    });
+                }
+            }
+            return;
+        }
+
+        let button = self.buttons[k];
+        for m in 0..=self.limit(button, rest) {
+            presses[k] = m;
+            self.run(
+                k + 1,
+                rest - button * m as isize,
+                tokens + self.tokens[k] * m,
+                presses,
+                best,
+            );
+        }
+        presses[k] = 0;
+    }
+
+    // The cheapest (m, a, b) with m * C + a * A + b * B == rest, where A
+    // and B are independent. By Cramer's rule a * det and b * det are
+    // linear in m, so a and b are integers exactly when m is in some
+    // arithmetic progression r + q * t. Along it m, a, b and the cost are
+    // all linear in t, so the best t is at one end of the range that
+    // keeps every press count in bounds.
+    fn solve_free(
+        &self,
+        C: Point,
+        A: Point,
+        B: Point,
+        rest: Point,
+        tokens: [i128; 3],
+    ) -> Option<(i128, i128, i128)> {
+        let cross = |p: Point, q: Point| p.i as i128 * q.j as i128 - q.i as i128 * p.j as i128;
+        let det = cross(A, B);
+        // a * det == a0 - m * a1, b * det == b0 - m * b1
+        let (a0, a1) = (cross(rest, B), cross(C, B));
+        let (b0, b1) = (cross(A, rest), cross(A, C));
+
+        let d = det.abs();
+        let (r, q) = crt(congruence(a1, a0, d)?, congruence(b1, b0, d)?)?;
+        let (m0, dm) = (r, q);
+        let (ua, va) = ((a0 - r * a1) / det, -(q * a1) / det);
+        let (ub, vb) = ((b0 - r * b1) / det, -(q * b1) / det);
+
+        let (mut lo, mut hi) = (0, i128::MAX);
+        let max = self.max_presses;
+        narrow(m0, dm, 0, Some(self.limit(C, rest)), &mut lo, &mut hi);
+        narrow(ua, va, 0, max, &mut lo, &mut hi);
+        narrow(ub, vb, 0, max, &mut lo, &mut hi);
+        if lo > hi {
+            return None;
         }
+        let slope = tokens[0] * dm + tokens[1] * va + tokens[2] * vb;
+        let t = if slope >= 0 { lo } else { hi };
+        Some((m0 + t * dm, ua + t * va, ub + t * vb))
     }
 }
 
-impl Matrix {
-    fn solve(&self, rhs: &Point) -> Option<Point> {
-        let inv: Matrix = self.inverse_nodet();
+// x with a * x == b (mod m), as x == r (mod q), for m > 0
+fn congruence(a: i128, b: i128, m: i128) -> Option<(i128, i128)> {
+    let (a, b) = (a.rem_euclid(m), b.rem_euclid(m));
+    let (g, u, _) = ext_gcd(a, m);
+    if b % g != 0 {
+        return None;
+    }
+    let q = m / g;
+    Some(((u * (b / g)).rem_euclid(q), q))
+}
 
-        let sol = Point {
-            i: inv.a[0][0] * rhs.i + inv.a[0][1] * rhs.j,
-            j: inv.a[1][0] * rhs.i + inv.a[1][1] * rhs.j,
-        };
+// x in both progressions, as a single one
+fn crt((r1, q1): (i128, i128), (r2, q2): (i128, i128)) -> Option<(i128, i128)> {
+    // r1 + q1 * s == r2 (mod q2)
+    let (s, q) = congruence(q1, r2 - r1, q2)?;
+    let q = q1 * q;
+    Some(((r1 + q1 * s).rem_euclid(q), q))
+}
+
+// Narrow [lo, hi] to the t with min <= u + t * v <= max
+fn narrow(u: i128, v: i128, min: i128, max: Option<i128>, lo: &mut i128, hi: &mut i128) {
+    let max = max.unwrap_or(i128::MAX);
+    if v == 0 {
+        if u < min || u > max {
+            *lo = 1;
+            *hi = 0;
+        }
+    } else if v > 0 {
+        *lo = (*lo).max(ceil_div(min - u, v));
+        if max != i128::MAX {
+            *hi = (*hi).min((max - u).div_euclid(v));
+        }
+    } else {
+        *hi = (*hi).min((u - min).div_euclid(-v));
+        if max != i128::MAX {
+            *lo = (*lo).max(ceil_div(u - max, -v));
+        }
+    }
+}
+
+// (g, x, y) with a * x + b * y == g == gcd(a, b), for a, b >= 0
+fn ext_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        (a, 1, 0)
+    } else {
+        let (g, x, y) = ext_gcd(b, a % b);
+        (g, y, x - (a / b) * y)
+    }
+}
+
+// ceil(a / b) for b > 0
+fn ceil_div(a: i128, b: i128) -> i128 {
+    a.div_euclid(b) + (a.rem_euclid(b) != 0) as i128
+}
+
+impl Matrix {
+    // The cheapest presses (a, b), each between 0 and `max`, with
+    // a * A + b * B == rhs, where A and B are the columns. All the
+    // arithmetic is in i128, so the part 2 offsets have plenty of room.
+    fn solve(&self, rhs: &Point, tokens: [i128; 2], max: Option<i128>) -> Option<(i128, i128)> {
+        let m = self.a.map(|row| row.map(|x| x as i128));
+        let (pi, pj) = (rhs.i as i128, rhs.j as i128);
 
         let det = self.det();
-        if sol % det == (Point { i: 0, j: 0 }) {
-            Some(sol / det)
+        let (a, b) = if det != 0 {
+            // a unique solution, if it's integer
+            let a = m[1][1] * pi - m[0][1] * pj;
+            let b = m[0][0] * pj - m[1][0] * pi;
+            if a % det != 0 || b % det != 0 {
+                return None;
+            }
+            (a / det, b / det)
         } else {
-            None
+            // A and B are collinear: solve along one coordinate where
+            // they aren't both 0, then check the prize is on their line
+            let row = if m[0] != [0, 0] { 0 } else { 1 };
+            let p = if row == 0 { pi } else { pj };
+            Self::solve_line(m[row][0], m[row][1], p, tokens, max)?
+        };
+
+        if a < 0 || b < 0 || a * m[0][0] + b * m[0][1] != pi || a * m[1][0] + b * m[1][1] != pj {
+            return None;
         }
+        if max.is_some_and(|max| a > max || b > max) {
+            return None;
+        }
+        Some((a, b))
     }
 
-    fn inverse_nodet(&self) -> Matrix {
-        Matrix {
-            a: [[self.a[1][1], -self.a[0][1]], [-self.a[1][0], self.a[0][0]]],
+    // The cheapest a, b >= 0 with a * x + b * y == p. With g = gcd(x, y),
+    // every solution is (a0 + t * y / g, b0 - t * x / g) for one particular
+    // (a0, b0), and the cost is linear in t, so the best one is at
+    // whichever end of the range of t keeps both between 0 and `max`.
+    fn solve_line(
+        x: i128,
+        y: i128,
+        p: i128,
+        tokens: [i128; 2],
+        max: Option<i128>,
+    ) -> Option<(i128, i128)> {
+        if x == 0 && y == 0 {
+            return (p == 0).then_some((0, 0));
+        }
+        let (g, u, v) = ext_gcd(x, y);
+        if p % g != 0 {
+            return None;
         }
+        let (a0, b0) = (u * (p / g), v * (p / g));
+        let (da, db) = (y / g, x / g);
+
+        // a0 + t * da >= 0 and b0 - t * db >= 0
+        let mut t_min = (da != 0).then(|| ceil_div(-a0, da));
+        let mut t_max = (db != 0).then(|| b0.div_euclid(db));
+        if let Some(max) = max {
+            // a0 + t * da <= max and b0 - t * db <= max
+            if da != 0 {
+                t_max = Some(t_max.map_or(i128::MAX, |t| t).min((max - a0).div_euclid(da)));
+            }
+            if db != 0 {
+                t_min = Some(t_min.map_or(i128::MIN, |t| t).max(ceil_div(b0 - max, db)));
+            }
+        }
+        let slope = tokens[0] * da - tokens[1] * db;
+        let t = match (t_min, t_max) {
+            (Some(lo), Some(hi)) if lo > hi => return None,
+            (Some(lo), _) if slope > 0 => lo,
+            (_, Some(hi)) => hi,
+            (Some(lo), None) => lo,
+            (None, None) => unreachable!(),
+        };
+        Some((a0 + t * da, b0 - t * db))
     }
 
-    fn det(&self) -> isize {
-        self.a[0][0] * self.a[1][1] - self.a[0][1] * self.a[1][0]
+    fn det(&self) -> i128 {
+        self.a[0][0] as i128 * self.a[1][1] as i128 - self.a[0][1] as i128 * self.a[1][0] as i128
     }
 }
 
 impl Arcade {
-    fn from_str(txt: &str, part2: bool) -> Self {
-        Arcade {
-            machines: txt
-                .split("\n\n")
-                .map(|m| Machine::from_str(m, part2))
-                .collect(),
-            part2,
+    fn from_str(txt: &str, params: Params) -> Self {
+        let machines: Vec<Machine> = txt
+            .split("\n\n")
+            .map(|m| Machine::from_str(m, params.offset))
+            .collect();
+        assert!(
+            params.tokens.iter().all(|&t| t >= 0),
+            "token costs can't be negative: {:?}",
+            params.tokens
+        );
+        for (k, m) in machines.iter().enumerate() {
+            assert!(
+                m.buttons.len() <= params.tokens.len(),
+                "machine {} has {} buttons, but there are only {} token costs",
+                k,
+                m.buttons.len(),
+                params.tokens.len()
+            );
         }
+        Arcade { machines, params }
     }
 
-    fn tokens_to_solve(&self) -> isize {
-        self.machines.iter().map(Machine::tokens_to_solve).sum()
+    fn solutions(&self) -> Vec<Option<Solution>> {
+        self.machines.iter().map(|m| m.solve(&self.params)).collect()
+    }
+
+    fn tokens_to_solve(&self) -> i128 {
+        self.solutions().iter().flatten().map(|sol| sol.tokens).sum()
     }
 }
 
-fn part1(txt: &str) -> isize {
-    let arcade = Arcade::from_str(txt, false);
+fn part1(txt: &str) -> i128 {
+    let arcade = Arcade::from_str(txt, Params::part1());
     arcade.tokens_to_solve()
 }
 
-fn part2(txt: &str) -> isize {
-    let arcade = Arcade::from_str(txt, true);
+fn part2(txt: &str) -> i128 {
+    let arcade = Arcade::from_str(txt, Params::part2());
     arcade.tokens_to_solve()
 }
 
+// `tokens` is comma separated, one per button
+fn print_solutions(txt: &str, tokens: &str, max_presses: &str, offset: &str) {
+    let offset: isize = offset.parse().unwrap();
+    let params = Params {
+        tokens: tokens.split(',').map(|t| t.parse().unwrap()).collect(),
+        max_presses: (max_presses != "none").then(|| max_presses.parse().unwrap()),
+        offset: Point {
+            i: offset,
+            j: offset,
+        },
+    };
+    let arcade = Arcade::from_str(txt, params);
+    for (k, sol) in arcade.solutions().iter().enumerate() {
+        match sol {
+            Some(sol) => println!(
+                "machine {:>3}: {} tokens, presses {:?}",
+                k, sol.tokens, sol.presses
+            ),
+            None => println!("machine {:>3}: no way to win", k),
+        }
+    }
+    println!("Total tokens: {}", arcade.tokens_to_solve());
+}
+
 fn main() {
     let day_x = env!("CARGO_BIN_NAME");
     let root = env!("CARGO_MANIFEST_DIR");
@@ -168,6 +530,13 @@ fn main() {
     // let path = String::from(root) + "/src/" + day_x + "/test1.txt";
     let txt = fs::read_to_string(path).unwrap();
 
+    // `cargo run --bin day13 -- custom TOKENS MAX_PRESSES OFFSET`
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(|a| a.as_str()) == Some("custom") {
+        print_solutions(&txt, &args[2], &args[3], &args[4]);
+        return;
+    }
+
     println!("This is {}", day_x);
     println!("Part 1: {:?}", part1(&txt));
     println!("Part 2: {:?}", part2(&txt));
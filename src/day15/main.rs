@@ -1,43 +1,41 @@
-use std::{collections::{HashMap, HashSet}, fs};
+mod solver;
 
-use regex::NoExpand;
+use solver::{SolveError, Solver, Target};
+use std::{
+    collections::HashMap,
+    env, fs,
+    io::{self, BufRead},
+};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Warehouse {
     map: HashMap<Point, usize>,
     objects: Vec<Object>,
-    robot: Point,
-    part2: bool,
+    // cell offsets of each distinct object shape, relative to the
+    // object's pos; shapes[0] is a single cell
+    shapes: Vec<Vec<Point>>,
+    // robot k is at robots[k], ordered by number; labels[k] is its number
+    // and how it's drawn ('@' or that digit)
+    robots: Vec<Point>,
+    labels: Vec<(usize, char)>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 struct Object {
     kind: ObjectKind,
+    // the first cell of the object in reading order
     pos: Point,
-}
-
-impl Object {
-    fn from_char(c: char, pos: Point) -> Self {
-        let kind = match c {
-            '.' => ObjectKind::Empty,
-            '#' => ObjectKind::Wall,
-            'O' => ObjectKind::Box,
-            '@' => ObjectKind::Robot,
-            _ => panic!("Invalid object: {}", c),
-        };
-        Self { kind, pos }
-    }
+    shape: usize,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 enum ObjectKind {
-    Empty,
     Wall,
     Box,
     Robot,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
 struct Point {
     i: isize,
     j: isize,
@@ -61,6 +59,24 @@ impl Dir {
         }
     }
 
+    fn opposite(&self) -> Dir {
+        match self {
+            Dir::Left => Dir::Right,
+            Dir::Right => Dir::Left,
+            Dir::Up => Dir::Down,
+            Dir::Down => Dir::Up,
+        }
+    }
+
+    fn as_char(&self) -> char {
+        match self {
+            Dir::Left => '<',
+            Dir::Right => '>',
+            Dir::Up => '^',
+            Dir::Down => 'v',
+        }
+    }
+
     fn from_char(c: char) -> Self {
         match c {
             '<' => Dir::Left,
@@ -100,86 +116,212 @@ impl std::ops::Sub<Point> for Point {
     }
 }
 
+// What happens when two robots push the same box in one round
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Conflict {
+    // each robot sees the warehouse as the previous one left it, so a box
+    // can be pushed more than once per round
+    Sequential,
+    // the first robot to push a box has it for the rest of the round, and
+    // anyone else who tries is blocked
+    FirstWins,
+}
+
+#[derive(Debug, Clone)]
+struct TraceEntry {
+    round: usize,
+    // the robot's number, as on the map
+    robot: usize,
+    dir: Dir,
+    from: Point,
+    // where the boxes it pushed were, or None if the robot was blocked
+    pushed: Option<Vec<Point>>,
+}
+
+// Labels for boxes that are neither 'O' nor "[]" when printing
+const BOX_LABELS: &[u8] = b"ABCDEFGHIJKLMNPQRSTUVWXYZ";
+
 impl Warehouse {
-    fn from_str(txt: &str, part2: bool) -> Self {
-        let mut map: HashMap<Point, usize> = HashMap::new();
-        let mut objects = vec![];
-        let mut robot = Point { i: 0, j: 0 };
-
-        for (i, line) in txt.lines().enumerate() {
-            for (mut j, c) in line.chars().enumerate() {
-                if part2 {
-                    j *= 2;
-                }
+    // Every cell of the map is stretched `widen` times to the right, so
+    // part 2 is widen = 2. Boxes are 'O' (one cell), "[]" (two cells side
+    // by side), or any other letter: each connected group of cells with
+    // the same letter is a single box of that shape. Robots are '@' or a
+    // digit.
+    fn from_str(txt: &str, widen: usize) -> Self {
+        let grid: Vec<Vec<char>> = txt.lines().map(|l| l.chars().collect()).collect();
+        let at = |p: Point| -> Option<char> {
+            if p.i < 0 || p.j < 0 {
+                return None;
+            }
+            grid.get(p.i as usize)?.get(p.j as usize).copied()
+        };
+        let right = Dir::Right.as_point();
+
+        // which box each box cell of the unwidened map belongs to
+        let mut box_of: HashMap<Point, usize> = HashMap::new();
+        let mut n_boxes = 0;
+        for (i, row) in grid.iter().enumerate() {
+            for (j, &c) in row.iter().enumerate() {
                 let pos = Point {
                     i: i as isize,
                     j: j as isize,
                 };
-                let obj = Object::from_char(c, pos);
-                if obj.kind != ObjectKind::Empty {
-                    map.insert(pos, objects.len());
-                    if part2 && obj.kind != ObjectKind::Robot {
-                        map.insert(pos + Point { i: 0, j: 1 }, objects.len());
+                if box_of.contains_key(&pos) {
+                    continue;
+                }
+                match c {
+                    '.' | '#' | '@' | '0'..='9' => continue,
+                    'O' => {
+                        box_of.insert(pos, n_boxes);
                     }
-                    objects.push(obj);
-
-                    if obj.kind == ObjectKind::Robot {
-                        robot = pos;
+                    '[' => {
+                        assert_eq!(at(pos + right), Some(']'), "Unmatched '[' at {:?}", pos);
+                        box_of.insert(pos, n_boxes);
+                        box_of.insert(pos + right, n_boxes);
                     }
+                    c if c.is_ascii_alphabetic() => {
+                        // flood fill the letter's group
+                        let mut stack = vec![pos];
+                        box_of.insert(pos, n_boxes);
+                        while let Some(p) = stack.pop() {
+                            for d in [Dir::Left, Dir::Right, Dir::Up, Dir::Down] {
+                                let q = p + d.as_point();
+                                if at(q) == Some(c) && !box_of.contains_key(&q) {
+                                    box_of.insert(q, n_boxes);
+                                    stack.push(q);
+                                }
+                            }
+                        }
+                    }
+                    _ => panic!("Invalid object: {}", c),
                 }
+                n_boxes += 1;
             }
         }
 
-        Self {
-            map,
-            objects,
-            robot,
-            part2,
+        let mut warehouse = Self {
+            map: HashMap::new(),
+            objects: vec![],
+            shapes: vec![vec![Point { i: 0, j: 0 }]],
+            robots: vec![],
+            labels: vec![],
+        };
+        let mut robots: Vec<(char, Point)> = vec![];
+        let mut box_cells: Vec<Vec<Point>> = vec![vec![]; n_boxes];
+        for (i, row) in grid.iter().enumerate() {
+            for (j, &c) in row.iter().enumerate() {
+                let pos = Point {
+                    i: i as isize,
+                    j: (j * widen) as isize,
+                };
+                let cells = (0..widen as isize).map(|k| pos + Point { i: 0, j: k });
+                match c {
+                    '.' => {}
+                    '#' => {
+                        for cell in cells {
+                            warehouse.add(ObjectKind::Wall, &[cell]);
+                        }
+                    }
+                    '@' | '0'..='9' => {
+                        warehouse.add(ObjectKind::Robot, &[pos]);
+                        robots.push((c, pos));
+                    }
+                    _ => {
+                        let id = box_of[&Point {
+                            i: i as isize,
+                            j: j as isize,
+                        }];
+                        box_cells[id].extend(cells);
+                    }
+                }
+            }
+        }
+        for cells in box_cells {
+            warehouse.add(ObjectKind::Box, &cells);
+        }
+        // digits are their own number, and each '@' (in reading order)
+        // takes the lowest number left
+        let mut numbered: Vec<(usize, char, Point)> = vec![];
+        for &(c, pos) in &robots {
+            if let Some(n) = c.to_digit(10) {
+                let n = n as usize;
+                assert!(numbered.iter().all(|r| r.0 != n), "two robots {}", n);
+                numbered.push((n, c, pos));
+            }
         }
+        for &(c, pos) in robots.iter().filter(|r| r.0 == '@') {
+            let n = (0..).find(|n| numbered.iter().all(|r| r.0 != *n)).unwrap();
+            numbered.push((n, c, pos));
+        }
+        numbered.sort();
+        warehouse.robots = numbered.iter().map(|r| r.2).collect();
+        warehouse.labels = numbered.iter().map(|r| (r.0, r.1)).collect();
+
+        warehouse
     }
 
-    fn neighbors(&self, obj: Object, dir: Dir) -> Vec<Object> {
-        let to = obj.pos + dir.as_point();
-        let next_obj_poses: Vec<Point> = if self.part2 && obj.kind != ObjectKind::Robot {
-            match dir {
-                Dir::Left => vec![to],
-                Dir::Right => vec![to + dir.as_point()],
-                Dir::Up | Dir::Down => vec![to, to + Point { i:0, j: 1 }],
+    // Add an object covering `cells`, sharing its shape with any earlier
+    // object of the same shape
+    fn add(&mut self, kind: ObjectKind, cells: &[Point]) {
+        let pos = *cells.iter().min().unwrap();
+        let mut offsets: Vec<Point> = cells.iter().map(|&c| c - pos).collect();
+        offsets.sort();
+        let shape = match self.shapes.iter().position(|s| *s == offsets) {
+            Some(shape) => shape,
+            None => {
+                self.shapes.push(offsets);
+                self.shapes.len() - 1
             }
-        } else {
-            vec![to]
         };
+        for &cell in cells {
+            self.map.insert(cell, self.objects.len());
+        }
+        self.objects.push(Object { kind, pos, shape });
+    }
 
-        // uniquify by index
-        let indices = next_obj_poses
-            .iter()
-            .filter_map(|&pos| self.map.get(&pos))
-            .cloned()
-            .collect::<HashSet<_>>();
+    fn cells(&self, obj: Object) -> impl Iterator<Item = Point> + '_ {
+        self.shapes[obj.shape].iter().map(move |&d| obj.pos + d)
+    }
 
-        indices.iter().map(|&idx| self.objects[idx]).collect()
+    // Indices of the other objects right in front of obj, going in dir
+    fn neighbors(&self, obj: Object, dir: Dir) -> Vec<usize> {
+        let own = self.map[&obj.pos];
+        let mut indices = vec![];
+        for cell in self.cells(obj) {
+            if let Some(&idx) = self.map.get(&(cell + dir.as_point())) {
+                if idx != own && !indices.contains(&idx) {
+                    indices.push(idx);
+                }
+            }
+        }
+        indices
     }
 
-    fn can_push(&self, obj: Object, dir: Dir) -> bool {
-        let all_neigh_obj = self.neighbors(obj, dir);
-
-        for neigh_obj in all_neigh_obj {
-            match neigh_obj.kind {
-                ObjectKind::Wall => return false,
-                ObjectKind::Box => {
-                    if !self.can_push(neigh_obj, dir) {
-                        return false;
-                    }
+    // Every object that moves if obj moves in dir, obj included, or None
+    // if that would shove something into a wall
+    fn pushed_by(&self, obj: Object, dir: Dir) -> Option<Vec<usize>> {
+        let mut moved = vec![self.map[&obj.pos]];
+        let mut k = 0;
+        while k < moved.len() {
+            for idx in self.neighbors(self.objects[moved[k]], dir) {
+                match self.objects[idx].kind {
+                    // robots don't get shoved around by other robots
+                    ObjectKind::Wall | ObjectKind::Robot => return None,
+                    ObjectKind::Box if !moved.contains(&idx) => moved.push(idx),
+                    _ => {}
                 }
-                _ => {}
             }
+            k += 1;
         }
+        Some(moved)
+    }
 
-        true
+    fn can_push(&self, obj: Object, dir: Dir) -> bool {
+        self.pushed_by(obj, dir).is_some()
     }
 
     fn try_push(&mut self, obj: Object, dir: Dir) -> bool {
-        // recursively push box in direction
+        // push everything in the way in direction
         // returns if actually moved
 
         if !self.can_push(obj, dir) {
@@ -191,57 +333,132 @@ impl Warehouse {
     }
 
     fn push(&mut self, obj: Object, dir: Dir) {
-        let all_neigh_obj = self.neighbors(obj, dir);
+        let moved = self.pushed_by(obj, dir).expect("Invalid push");
+        self.shift(&moved, dir);
+    }
 
-        for neigh_obj in all_neigh_obj {
-            match neigh_obj.kind {
-                ObjectKind::Wall => panic!("Invalid push"),
-                ObjectKind::Box => self.push(neigh_obj, dir),
-                _ => {}
+    fn shift(&mut self, moved: &[usize], dir: Dir) {
+        // lift everything off the map before putting it back, so objects
+        // moving into each other's cells don't clash
+        for &idx in moved {
+            let cells: Vec<Point> = self.cells(self.objects[idx]).collect();
+            for cell in cells {
+                self.map.remove(&cell);
+            }
+        }
+        for &idx in moved {
+            self.objects[idx].pos += dir.as_point();
+            let cells: Vec<Point> = self.cells(self.objects[idx]).collect();
+            for cell in cells {
+                self.map.insert(cell, idx);
             }
         }
-
-        self.move_one(obj, obj.pos + dir.as_point());
     }
 
-    fn get(&self, pos: Point) -> Option<&Object> {
-        if let Some(idx) = self.map.get(&pos) {
-            self.objects.get(*idx)
+    // Move the (first) robot one step, pushing whatever's in the way.
+    // Returns whether it moved.
+    fn step(&mut self, dir: Dir) -> bool {
+        let robot = *self.get(self.robots[0]).unwrap();
+        if self.try_push(robot, dir) {
+            self.robots[0] += dir.as_point();
+            true
         } else {
-            None
+            false
         }
     }
 
-    fn get_mut(&mut self, pos: Point) -> Option<&mut Object> {
-        if let Some(idx) = self.map.get(&pos) {
-            self.objects.get_mut(*idx)
-        } else {
-            None
+    // Like `step`, but returns everything that moved (the robot first),
+    // or None if the robot was blocked, so it can be undone
+    fn step_moved(&mut self, dir: Dir) -> Option<Vec<usize>> {
+        let robot = *self.get(self.robots[0]).unwrap();
+        let moved = self.pushed_by(robot, dir)?;
+        self.restep(dir, &moved);
+        Some(moved)
+    }
+
+    // Shift what `step_moved` moved again, or back
+    fn restep(&mut self, dir: Dir, moved: &[usize]) {
+        self.shift(moved, dir);
+        self.robots[0] += dir.as_point();
+    }
+
+    fn unstep(&mut self, dir: Dir, moved: &[usize]) {
+        self.shift(moved, dir.opposite());
+        self.robots[0] = self.robots[0] - dir.as_point();
+    }
+
+    // Move robot r one step, unless that would push one of the `locked`
+    // boxes. Returns the boxes it pushed, or None if it was blocked.
+    fn step_robot(&mut self, r: usize, dir: Dir, locked: &[usize]) -> Option<Vec<usize>> {
+        let robot = *self.get(self.robots[r]).unwrap();
+        let moved = self.pushed_by(robot, dir)?;
+        if moved.iter().any(|idx| locked.contains(idx)) {
+            return None;
         }
+        self.shift(&moved, dir);
+        self.robots[r] += dir.as_point();
+        // moved[0] is the robot itself
+        Some(moved[1..].to_vec())
     }
 
-    fn move_one(&mut self, obj: Object, to: Point) {
-        // move, assuming the destination is empty
-        let idx = self.map.remove(&obj.pos).unwrap();
-        let wide = self.part2 && obj.kind != ObjectKind::Robot;
-        if wide {
-            let idx2 = self.map.remove(&(obj.pos + Point { i: 0, j: 1 })).unwrap();
-            assert_eq!(idx, idx2);
+    // Run every robot's script, round-robin: in each round, the robots
+    // make their next move in order of number, if they have one left.
+    // scripts[k] is robot k's, as in `robots`. Robots never push each
+    // other; a robot that would is blocked.
+    fn run_crew(&mut self, scripts: &[Vec<Dir>], conflict: Conflict) -> Vec<TraceEntry> {
+        let rounds = scripts.iter().map(|s| s.len()).max().unwrap_or(0);
+        let mut trace = vec![];
+        for round in 0..rounds {
+            let mut locked = vec![];
+            for (robot, script) in scripts.iter().enumerate() {
+                let Some(&dir) = script.get(round) else {
+                    continue;
+                };
+                let from = self.robots[robot];
+                let pushed = self.step_robot(robot, dir, &locked);
+                if let Some(pushed) = &pushed {
+                    if conflict == Conflict::FirstWins {
+                        locked.extend(pushed);
+                    }
+                }
+                trace.push(TraceEntry {
+                    round,
+                    robot: self.labels[robot].0,
+                    dir,
+                    from,
+                    pushed: pushed.map(|boxes| {
+                        boxes
+                            .iter()
+                            .map(|&idx| self.objects[idx].pos - dir.as_point())
+                            .collect()
+                    }),
+                });
+            }
         }
+        trace
+    }
 
-        self.map.insert(to, idx);
-        if wide {
-            self.map.insert(to + Point { i: 0, j: 1 }, idx);
+    fn get(&self, pos: Point) -> Option<&Object> {
+        if let Some(idx) = self.map.get(&pos) {
+            self.objects.get(*idx)
+        } else {
+            None
         }
+    }
 
-        self.objects[idx].pos = to;
+    // A box's GPS coordinate measures from the top and left edges of the
+    // box, i.e. the corner of its bounding box
+    fn gps(&self, obj: Object) -> isize {
+        let top = self.cells(obj).map(|c| c.i).min().unwrap();
+        let left = self.cells(obj).map(|c| c.j).min().unwrap();
+        100 * top + left
     }
 
     fn score(&self) -> isize {
         self.objects
             .iter()
             .filter(|obj| obj.kind == ObjectKind::Box)
-            .map(|obj| 100 * obj.pos.i + obj.pos.j)
+            .map(|&obj| self.gps(obj))
             .sum()
     }
 }
@@ -254,19 +471,31 @@ impl std::fmt::Display for Warehouse {
             max_i = max_i.max(pos.i);
             max_j = max_j.max(pos.j);
         }
+        let pair = [Point { i: 0, j: 0 }, Point { i: 0, j: 1 }];
 
         for i in 0..=max_i {
             for j in 0..=max_j {
                 let pos = Point { i, j };
-                let kind = self
-                    .get(pos)
-                    .map(|obj| obj.kind)
-                    .unwrap_or(ObjectKind::Empty);
-                let c = match kind {
-                    ObjectKind::Empty => '.',
+                let Some(&idx) = self.map.get(&pos) else {
+                    write!(f, ".")?;
+                    continue;
+                };
+                let obj = self.objects[idx];
+                let c = match obj.kind {
                     ObjectKind::Wall => '#',
-                    ObjectKind::Box => 'O',
-                    ObjectKind::Robot => '@',
+                    ObjectKind::Robot => match self.robots.iter().position(|&p| p == pos) {
+                        Some(r) => self.labels[r].1,
+                        None => '@',
+                    },
+                    ObjectKind::Box if obj.shape == 0 => 'O',
+                    ObjectKind::Box if self.shapes[obj.shape] == pair => {
+                        if pos == obj.pos {
+                            '['
+                        } else {
+                            ']'
+                        }
+                    }
+                    ObjectKind::Box => BOX_LABELS[idx % BOX_LABELS.len()] as char,
                 };
                 write!(f, "{}", c)?;
             }
@@ -284,45 +513,308 @@ fn get_moves(txt: &str) -> Vec<Dir> {
         .collect()
 }
 
-fn part1(txt: &str) -> isize {
+// One script per robot, in the order of `warehouse.robots`. A line
+// "K: <moves>" belongs to robot number K, and lines without a prefix to
+// the lowest numbered robot.
+fn get_scripts(txt: &str, warehouse: &Warehouse) -> Vec<Vec<Dir>> {
+    let mut scripts = vec![vec![]; warehouse.robots.len()];
+    for line in txt.lines() {
+        let (robot, moves) = match line.split_once(':') {
+            Some((k, moves)) => {
+                let k: usize = k.trim().parse().unwrap();
+                let robot = warehouse.labels.iter().position(|l| l.0 == k);
+                (robot.unwrap_or_else(|| panic!("no robot {}", k)), moves.trim())
+            }
+            None => (0, line),
+        };
+        scripts[robot].extend(moves.chars().map(Dir::from_char));
+    }
+    scripts
+}
+
+fn print_crew(txt: &str, widen: usize, conflict: Conflict) {
     let mut parts = txt.split("\n\n");
-    let mut warehouse = Warehouse::from_str(parts.next().unwrap(), false);
+    let mut warehouse = Warehouse::from_str(parts.next().unwrap(), widen);
+    let scripts = get_scripts(parts.next().unwrap(), &warehouse);
     println!("{}", warehouse);
 
-    let moves = get_moves(parts.next().unwrap());
+    let trace = warehouse.run_crew(&scripts, conflict);
+    for entry in &trace {
+        let what = match &entry.pushed {
+            None => "blocked".to_string(),
+            Some(pushed) if pushed.is_empty() => "moved".to_string(),
+            Some(pushed) => {
+                let boxes: Vec<String> = pushed.iter().map(|p| format!("{},{}", p.i, p.j)).collect();
+                format!("pushed the boxes at {}", boxes.join(" "))
+            }
+        };
+        println!(
+            "round {:>4}: robot {} {:?} from {},{}: {}",
+            entry.round, entry.robot, entry.dir, entry.from.i, entry.from.j, what
+        );
+    }
+    println!("{}", warehouse);
+    println!("Score: {}", warehouse.score());
+}
 
-    for dir in moves {
-        let robot = *warehouse.get(warehouse.robot).unwrap();
-        if warehouse.try_push(robot, dir) {
-            warehouse.robot += dir.as_point();
+// `goal` is either "score N" or a file with the map to reach (only the
+// boxes of which matter)
+fn print_solution(txt: &str, widen: usize, goal: &[String]) {
+    let start = Warehouse::from_str(txt.split("\n\n").next().unwrap(), widen);
+    let (target, rest) = if goal[0] == "score" {
+        (Target::Score(goal[1].parse().unwrap()), &goal[2..])
+    } else {
+        let target_txt = fs::read_to_string(&goal[0]).unwrap();
+        let target = Warehouse::from_str(target_txt.split("\n\n").next().unwrap(), widen);
+        (Target::layout_of(&target, &start), &goal[1..])
+    };
+    let mut solver = Solver::new(target);
+    if let Some(limit) = rest.first() {
+        solver.node_limit = limit.parse().unwrap();
+    }
+    solver.report_every = Some(100_000);
+
+    println!("{}", start);
+    match solver.solve(&start) {
+        Ok(moves) => {
+            let mut warehouse = start.clone();
+            for c in moves.chars() {
+                warehouse.step(Dir::from_char(c));
+            }
+            println!("{}", warehouse);
+            println!("{} moves: {}", moves.len(), moves);
+        }
+        Err(SolveError::Unreachable { explored }) => {
+            println!("Unreachable (explored {} states)", explored)
+        }
+        Err(SolveError::NodeLimit { explored }) => {
+            println!("Gave up after {} states", explored)
         }
     }
-    println!("{}", warehouse);
-    warehouse.score()
+}
+
+fn part1(txt: &str) -> isize {
+    final_score(txt, 1)
 }
 
 fn part2(txt: &str) -> isize {
+    // (`cargo run --bin day15 -- play 2` to step through by hand)
+    final_score(txt, 2)
+}
+
+fn final_score(txt: &str, widen: usize) -> isize {
     let mut parts = txt.split("\n\n");
-    let mut warehouse = Warehouse::from_str(parts.next().unwrap(), true);
+    let mut warehouse = Warehouse::from_str(parts.next().unwrap(), widen);
     println!("{}", warehouse);
 
     let moves = get_moves(parts.next().unwrap());
 
     for dir in moves {
-        // println!("{:?}", dir);
-        // wait for input
-        // let mut input = String::new();
-        // std::io::stdin().read_line(&mut input).unwrap();
-        let robot = *warehouse.get(warehouse.robot).unwrap();
-        if warehouse.try_push(robot, dir) {
-            warehouse.robot += dir.as_point();
-        }
-        // println!("{}", warehouse);
+        warehouse.step(dir);
     }
     // println!("{}", warehouse);
     warehouse.score()
 }
 
+// One change in the play history, with just enough to undo and redo it
+enum Change {
+    // the robot tried to move; `moved` is what got shifted, or None if it
+    // was blocked
+    Step { dir: Dir, moved: Option<Vec<usize>> },
+    // a jump to input move `to`, keeping the state it jumped from
+    Jump { before: Box<Warehouse>, to: usize },
+}
+
+// The warehouse as it is now, and the changes leading up to it for
+// undo/redo. Each change also records how far into the input's moves it
+// leaves things.
+struct History {
+    start: Warehouse,
+    script: Vec<Dir>,
+    current: Warehouse,
+    changes: Vec<(Change, usize)>,
+    // changes[..cursor] have been applied
+    cursor: usize,
+}
+
+impl History {
+    fn new(start: Warehouse, script: Vec<Dir>) -> Self {
+        History {
+            current: start.clone(),
+            start,
+            script,
+            changes: vec![],
+            cursor: 0,
+        }
+    }
+
+    // position in the input's moves
+    fn pos(&self) -> usize {
+        match self.cursor {
+            0 => 0,
+            k => self.changes[k - 1].1,
+        }
+    }
+
+    // a new change drops anything that could have been redone
+    fn push(&mut self, change: Change, pos: usize) {
+        self.changes.truncate(self.cursor);
+        self.changes.push((change, pos));
+        self.cursor += 1;
+    }
+
+    // Move the robot, counting it as the next input move if `scripted`.
+    // Returns whether it moved.
+    fn step(&mut self, dir: Dir, scripted: bool) -> bool {
+        let pos = self.pos() + scripted as usize;
+        let moved = self.current.step_moved(dir);
+        let stepped = moved.is_some();
+        self.push(Change::Step { dir, moved }, pos);
+        stepped
+    }
+
+    fn after_moves(&self, n: usize) -> Warehouse {
+        let mut warehouse = self.start.clone();
+        for &dir in &self.script[..n] {
+            warehouse.step(dir);
+        }
+        warehouse
+    }
+
+    fn jump(&mut self, to: usize) {
+        let to = to.min(self.script.len());
+        let after = self.after_moves(to);
+        let before = std::mem::replace(&mut self.current, after);
+        self.push(
+            Change::Jump {
+                before: Box::new(before),
+                to,
+            },
+            to,
+        );
+    }
+
+    fn undo(&mut self) -> bool {
+        if self.cursor == 0 {
+            return false;
+        }
+        self.cursor -= 1;
+        match &self.changes[self.cursor].0 {
+            Change::Step {
+                dir,
+                moved: Some(moved),
+            } => self.current.unstep(*dir, moved),
+            Change::Step { moved: None, .. } => {}
+            Change::Jump { before, .. } => self.current = (**before).clone(),
+        }
+        true
+    }
+
+    fn redo(&mut self) -> bool {
+        if self.cursor == self.changes.len() {
+            return false;
+        }
+        match &self.changes[self.cursor].0 {
+            Change::Step {
+                dir,
+                moved: Some(moved),
+            } => self.current.restep(*dir, moved),
+            Change::Step { moved: None, .. } => {}
+            Change::Jump { to, .. } => self.current = self.after_moves(*to),
+        }
+        self.cursor += 1;
+        true
+    }
+}
+
+// Moves typed at the prompt, as <^>v or arrow keys (which a line-buffered
+// terminal passes through as escape sequences)
+fn parse_typed_moves(line: &str) -> Vec<Dir> {
+    let mut dirs = vec![];
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '<' | '>' | '^' | 'v' => dirs.push(Dir::from_char(c)),
+            '\x1b' => {
+                if chars.next() != Some('[') {
+                    continue;
+                }
+                match chars.next() {
+                    Some('A') => dirs.push(Dir::Up),
+                    Some('B') => dirs.push(Dir::Down),
+                    Some('C') => dirs.push(Dir::Right),
+                    Some('D') => dirs.push(Dir::Left),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+    dirs
+}
+
+fn play(txt: &str, widen: usize) {
+    let mut parts = txt.split("\n\n");
+    let start = Warehouse::from_str(parts.next().unwrap(), widen);
+    let script = get_moves(parts.next().unwrap());
+    let mut history = History::new(start, script);
+
+    println!("Type moves (<^>v or arrow keys), u = undo, r = redo,");
+    println!("n [K] = next K moves of the input, g N = go to input move N, q = quit");
+    println!("{}", history.current);
+
+    for line in io::stdin().lock().lines() {
+        let line = line.unwrap();
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("q") => break,
+            Some("u") => {
+                if !history.undo() {
+                    println!("Nothing to undo");
+                }
+            }
+            Some("r") => {
+                if !history.redo() {
+                    println!("Nothing to redo");
+                }
+            }
+            Some("n") => {
+                let k: usize = words.next().and_then(|w| w.parse().ok()).unwrap_or(1);
+                for _ in 0..k {
+                    let Some(&dir) = history.script.get(history.pos()) else {
+                        println!("End of the input moves");
+                        break;
+                    };
+                    history.step(dir, true);
+                }
+            }
+            Some("g") => {
+                let Some(n) = words.next().and_then(|w| w.parse::<usize>().ok()) else {
+                    println!("g needs a move number");
+                    continue;
+                };
+                history.jump(n);
+            }
+            _ => {
+                for dir in parse_typed_moves(&line) {
+                    if !history.step(dir, false) {
+                        println!("Blocked moving {:?}", dir);
+                    }
+                    println!("{}", history.current);
+                }
+                continue;
+            }
+        }
+        println!("{}", history.current);
+        println!(
+            "input move {}/{}, score {}",
+            history.pos(),
+            history.script.len(),
+            history.current.score()
+        );
+    }
+}
+
 fn main() {
     let day_x = env!("CARGO_BIN_NAME");
     let root = env!("CARGO_MANIFEST_DIR");
@@ -333,7 +825,35 @@ fn main() {
     // let path = String::from(root) + "/src/" + day_x + "/test3.txt";
     let txt = fs::read_to_string(path).unwrap();
 
+    // `cargo run --bin day15 -- play [WIDEN]`
+    let args: Vec<String> = env::args().collect();
+    let widen = args.get(2).map_or(1, |w| w.parse().unwrap());
+    if args.get(1).map(|a| a.as_str()) == Some("play") {
+        play(&txt, widen);
+        return;
+    }
+    // `cargo run --bin day15 -- crew [WIDEN] [first-wins]`
+    if args.get(1).map(|a| a.as_str()) == Some("crew") {
+        let conflict = if args.get(3).map(|a| a.as_str()) == Some("first-wins") {
+            Conflict::FirstWins
+        } else {
+            Conflict::Sequential
+        };
+        print_crew(&txt, widen, conflict);
+        return;
+    }
+    // `cargo run --bin day15 -- solve WIDEN (score N | TARGET_FILE) [NODE_LIMIT]`
+    if args.get(1).map(|a| a.as_str()) == Some("solve") {
+        print_solution(&txt, widen, &args[3..]);
+        return;
+    }
+    // `cargo run --bin day15 -- widen WIDEN`
+    if args.get(1).map(|a| a.as_str()) == Some("widen") {
+        println!("Score with {}x widening: {}", widen, final_score(&txt, widen));
+        return;
+    }
+
     println!("This is {}", day_x);
-    // println!("Part 1: {:?}", part1(&txt));
+    println!("Part 1: {:?}", part1(&txt));
     println!("Part 2: {:?}", part2(&txt));
 }
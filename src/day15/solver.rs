@@ -0,0 +1,186 @@
+use crate::{Dir, ObjectKind, Point, Warehouse};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+const DIRS: [Dir; 4] = [Dir::Left, Dir::Right, Dir::Up, Dir::Down];
+
+// Where the boxes should end up
+#[derive(Debug, Clone)]
+pub enum Target {
+    // (shape, pos) of every box, sorted, as in `Warehouse::layout`
+    Layout(Vec<(usize, Point)>),
+    Score(isize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolveError {
+    // every reachable state was explored
+    Unreachable { explored: usize },
+    NodeLimit { explored: usize },
+}
+
+// Boxes of the same shape are interchangeable, so states are the robots'
+// positions plus the layout
+type State = (Vec<Point>, Vec<(usize, Point)>);
+
+impl Warehouse {
+    // (shape, pos) of every box, sorted
+    pub fn layout(&self) -> Vec<(usize, Point)> {
+        let mut layout: Vec<(usize, Point)> = self
+            .objects
+            .iter()
+            .filter(|obj| obj.kind == ObjectKind::Box)
+            .map(|obj| (obj.shape, obj.pos))
+            .collect();
+        layout.sort();
+        layout
+    }
+
+    fn state(&self) -> State {
+        (self.robots.clone(), self.layout())
+    }
+}
+
+impl Target {
+    // The box layout of `target`, with shapes numbered as in `start`
+    pub fn layout_of(target: &Warehouse, start: &Warehouse) -> Self {
+        let mut layout: Vec<(usize, Point)> = target
+            .layout()
+            .into_iter()
+            .map(|(shape, pos)| {
+                let offsets = &target.shapes[shape];
+                let shape = start
+                    .shapes
+                    .iter()
+                    .position(|s| s == offsets)
+                    .unwrap_or_else(|| panic!("no box of shape {:?} to start with", offsets));
+                (shape, pos)
+            })
+            .collect();
+        layout.sort();
+        Target::Layout(layout)
+    }
+
+    fn reached(&self, warehouse: &Warehouse) -> bool {
+        match self {
+            Target::Layout(layout) => warehouse.layout() == *layout,
+            Target::Score(score) => warehouse.score() == *score,
+        }
+    }
+}
+
+// Breadth-first search for the shortest move string taking the first
+// robot from `start` to the target. Any other robots stay put.
+pub struct Solver {
+    target: Target,
+    pub node_limit: usize,
+    // print progress to stderr every this many states
+    pub report_every: Option<usize>,
+}
+
+impl Solver {
+    pub fn new(target: Target) -> Self {
+        Solver {
+            target,
+            node_limit: 10_000_000,
+            report_every: None,
+        }
+    }
+
+    // For a layout target: the positions each box shape can be at and
+    // still get to one of that shape's target positions. It's found by
+    // pulling boxes back from the targets, counting only walls, so it
+    // never rules out a solvable state. Each push needs the shape to fit
+    // and the robot to stand behind one of its cells.
+    fn live_positions(&self, start: &Warehouse) -> Option<HashMap<usize, HashSet<Point>>> {
+        let Target::Layout(layout) = &self.target else {
+            // a score can be reached in too many ways to rule anything out
+            return None;
+        };
+        let wall = |p: Point| {
+            start
+                .get(p)
+                .is_some_and(|obj| obj.kind == ObjectKind::Wall)
+        };
+        let mut live: HashMap<usize, HashSet<Point>> = HashMap::new();
+        for &(shape, target) in layout {
+            let offsets = &start.shapes[shape];
+            let seen = live.entry(shape).or_default();
+            if !seen.insert(target) {
+                continue;
+            }
+            let mut stack = vec![target];
+            while let Some(pos) = stack.pop() {
+                for dir in DIRS {
+                    // the box came to pos from `from`, pushed in dir
+                    let from = pos - dir.as_point();
+                    let cells: Vec<Point> = offsets.iter().map(|&d| from + d).collect();
+                    if cells.iter().any(|&c| wall(c)) {
+                        continue;
+                    }
+                    let pushable = cells.iter().any(|&c| {
+                        let behind = c - dir.as_point();
+                        !cells.contains(&behind) && !wall(behind)
+                    });
+                    if pushable && seen.insert(from) {
+                        stack.push(from);
+                    }
+                }
+            }
+        }
+        Some(live)
+    }
+
+    pub fn solve(&self, start: &Warehouse) -> Result<String, SolveError> {
+        let live = self.live_positions(start);
+        let dead = |w: &Warehouse| {
+            live.as_ref().is_some_and(|live| {
+                w.layout().iter().any(|(shape, pos)| {
+                    live.get(shape).is_none_or(|positions| !positions.contains(pos))
+                })
+            })
+        };
+
+        // nodes[k] = (parent node, move from there)
+        let mut nodes: Vec<(usize, Dir)> = vec![(0, Dir::Left)];
+        let mut seen: HashSet<State> = HashSet::from([start.state()]);
+        let mut queue = VecDeque::from([(start.clone(), 0, 0)]);
+        while let Some((warehouse, node, depth)) = queue.pop_front() {
+            if self.target.reached(&warehouse) {
+                let mut moves = vec![];
+                let mut k = node;
+                while k != 0 {
+                    let (parent, dir) = nodes[k];
+                    moves.push(dir.as_char());
+                    k = parent;
+                }
+                return Ok(moves.iter().rev().collect());
+            }
+            for dir in DIRS {
+                let mut next = warehouse.clone();
+                if !next.step(dir) || dead(&next) || !seen.insert(next.state()) {
+                    continue;
+                }
+                nodes.push((node, dir));
+                if nodes.len() > self.node_limit {
+                    return Err(SolveError::NodeLimit {
+                        explored: seen.len(),
+                    });
+                }
+                if let Some(every) = self.report_every {
+                    if nodes.len().is_multiple_of(every) {
+                        eprintln!(
+                            "{} states, depth {}, {} queued",
+                            nodes.len(),
+                            depth + 1,
+                            queue.len()
+                        );
+                    }
+                }
+                queue.push_back((next, nodes.len() - 1, depth + 1));
+            }
+        }
+        Err(SolveError::Unreachable {
+            explored: seen.len(),
+        })
+    }
+}
@@ -1,10 +1,87 @@
+#[path = "../common/bignum.rs"]
+mod bignum;
+
+use bignum::BigUint;
 use std::cmp::Reverse;
 use std::collections::{BinaryHeap, HashSet};
-use std::{collections::HashMap, fs};
+use std::{collections::HashMap, env, fs};
+
+type State = (Point, Dir);
+
+// Every tile on some cheapest route from the start to the end, and how
+// many distinct cheapest routes (sequences of moves) there are
+struct BestPaths {
+    cost: isize,
+    tiles: HashSet<Point>,
+    count: BigUint,
+}
+
+impl BestPaths {
+    // for `Map::print_map`
+    fn overlay(&self) -> HashMap<Point, char> {
+        self.tiles.iter().map(|&p| (p, 'O')).collect()
+    }
+}
 
 struct Map {
     map: HashMap<Point, Tile>,
     start: Point,
+    costs: CostModel,
+}
+
+// What each move costs the reindeer
+#[derive(Debug, Clone)]
+struct CostModel {
+    forward: isize,
+    turn_left: isize,
+    turn_right: isize,
+    // None if the reindeer can only turn 90° at a time
+    u_turn: Option<isize>,
+    // extra cost for stepping onto each kind of tile
+    enter: HashMap<Tile, isize>,
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        CostModel {
+            forward: 1,
+            turn_left: 1000,
+            turn_right: 1000,
+            u_turn: None,
+            enter: HashMap::new(),
+        }
+    }
+}
+
+impl CostModel {
+    // Dijkstra and the cost-ordered path count in `best_paths` need every
+    // move to cost something; entering a tile may be free
+    fn check(&self) -> Result<(), String> {
+        let moves = [
+            ("forward", Some(self.forward)),
+            ("turn_left", Some(self.turn_left)),
+            ("turn_right", Some(self.turn_right)),
+            ("u_turn", self.u_turn),
+        ];
+        for (name, cost) in moves {
+            if let Some(c) = cost.filter(|&c| c < 1) {
+                return Err(format!("{} cost must be at least 1, not {}", name, c));
+            }
+        }
+        for (tile, &c) in &self.enter {
+            if c < 0 {
+                return Err(format!(
+                    "cost to enter {:?} must not be negative, not {}",
+                    tile, c
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn forward_onto(&self, tile: Tile) -> isize {
+        self.forward + self.enter.get(&tile).unwrap_or(&0)
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
@@ -56,6 +133,16 @@ impl Dir {
         }
     }
 
+    // position in `DIRS`
+    fn index(&self) -> usize {
+        match self {
+            Dir::Left => 0,
+            Dir::Right => 1,
+            Dir::Up => 2,
+            Dir::Down => 3,
+        }
+    }
+
     fn left90(&self) -> Dir {
         match self {
             Dir::Left => Dir::Down,
@@ -65,6 +152,10 @@ impl Dir {
         }
     }
 
+    fn reverse(&self) -> Dir {
+        self.left90().left90()
+    }
+
     fn right90(&self) -> Dir {
         match self {
             Dir::Left => Dir::Up,
@@ -98,17 +189,32 @@ impl Map {
                 map.insert(point, tile);
             }
         }
-        Self { map, start }
+        Self {
+            map,
+            start,
+            costs: CostModel::default(),
+        }
+    }
+
+    fn with_costs(self, costs: CostModel) -> Self {
+        if let Err(e) = costs.check() {
+            panic!("bad cost model: {}", e);
+        }
+        Self { costs, ..self }
     }
 
     fn moves(&self, pos: Point, dir: Dir) -> Vec<(Dir, isize)> {
         let mut moves = vec![];
+        // nothing goes back onto the start
         match self.get(pos + dir.as_point()) {
-            Tile::Empty | Tile::End => moves.push((dir, 1)),
-            _ => {}
+            Tile::Wall | Tile::Start => {}
+            tile => moves.push((dir, self.costs.forward_onto(tile))),
+        }
+        moves.push((dir.left90(), self.costs.turn_left));
+        moves.push((dir.right90(), self.costs.turn_right));
+        if let Some(c) = self.costs.u_turn {
+            moves.push((dir.reverse(), c));
         }
-        moves.push((dir.left90(), 1000));
-        moves.push((dir.right90(), 1000));
         moves
     }
 
@@ -148,57 +254,134 @@ impl Map {
         panic!("No path to end");
     }
 
-    fn count_all_tiles_on_best_paths(&self, pos: Point, dir: Dir) -> isize {
-        let mut heap = BinaryHeap::new();
-        heap.push(Reverse((0, vec![pos], dir)));
-
-        let mut visited = HashMap::new();
-        visited.insert((pos, dir), 0);
-
-        let mut tiles_on_best_paths: HashSet<Point> = HashSet::new();
-        let mut cost_to_end: Option<isize> = None;
-
-        while let Some(Reverse((cost, path, dir))) = heap.pop() {
-            // println!("cost: {}, pos: {}, dir: {:?}", cost, pos + Point { i: 1, j: 1 }, dir);
-            let pos = *path.last().unwrap();
-            if self.get(pos) == Tile::End {
-                tiles_on_best_paths.extend(path);
-                if cost_to_end.is_none() {
-                    cost_to_end = Some(cost);
-                } else {
-                    assert_eq!(cost, cost_to_end.unwrap());
-                }
-                continue;
-            }
-
-            for (new_dir, new_cost) in self.moves(pos, dir) {
+    // The states the moves from `(pos, dir)` lead to, with their costs
+    fn next_states(&self, (pos, dir): State) -> Vec<(State, isize)> {
+        self.moves(pos, dir)
+            .into_iter()
+            .map(|(new_dir, c)| {
                 let new_pos = if dir == new_dir {
                     pos + new_dir.as_point()
                 } else {
                     pos
                 };
-                let new_cost = cost + new_cost;
-                if let Some(&best_cost) = visited.get(&(new_pos, new_dir)) {
-                    if new_cost > best_cost {
-                        continue;
-                    }
-                }
-                if cost_to_end.is_some() && new_cost > cost_to_end.unwrap() {
+                ((new_pos, new_dir), c)
+            })
+            .collect()
+    }
+
+    // Moves into `(pos, dir)`, as (previous state, cost): the reverse of
+    // `moves`
+    fn moves_into(&self, pos: Point, dir: Dir) -> Vec<(State, isize)> {
+        let back = pos
+            + Point {
+                i: -dir.as_point().i,
+                j: -dir.as_point().j,
+            };
+        let mut moves = vec![];
+        if self.get(back) != Tile::Wall && self.get(pos) != Tile::Start {
+            moves.push(((back, dir), self.costs.forward_onto(self.get(pos))));
+        }
+        // turning left from dir.right90() faces dir, and so on
+        moves.push(((pos, dir.right90()), self.costs.turn_left));
+        moves.push(((pos, dir.left90()), self.costs.turn_right));
+        if let Some(c) = self.costs.u_turn {
+            moves.push(((pos, dir.reverse()), c));
+        }
+        moves
+    }
+
+    // Cheapest cost to every state from any of `sources`, either along
+    // the moves or, with `backward`, against them (i.e. cost to reach a
+    // source)
+    fn dijkstra(&self, sources: &[State], backward: bool) -> HashMap<State, isize> {
+        let mut dist = HashMap::new();
+        let mut heap = BinaryHeap::new();
+        for &s in sources {
+            dist.insert(s, 0);
+            heap.push(Reverse((0, s.0, s.1)));
+        }
+
+        while let Some(Reverse((cost, pos, dir))) = heap.pop() {
+            if dist[&(pos, dir)] < cost {
+                continue;
+            }
+            let next = if backward {
+                self.moves_into(pos, dir)
+            } else {
+                self.next_states((pos, dir))
+            };
+            for ((new_pos, new_dir), c) in next {
+                let new_cost = cost + c;
+                if dist
+                    .get(&(new_pos, new_dir))
+                    .is_some_and(|&best| best <= new_cost)
+                {
                     continue;
                 }
-                let mut new_path = path.clone();
-                new_path.push(new_pos);
-                visited.insert((new_pos, new_dir), new_cost);
-                heap.push(Reverse((new_cost, new_path, new_dir)));
+                dist.insert((new_pos, new_dir), new_cost);
+                heap.push(Reverse((new_cost, new_pos, new_dir)));
             }
         }
+        dist
+    }
 
-        // self.print_map(&tiles_on_best_paths);
+    // A state is on a cheapest route iff its cost from the start plus its
+    // cost to the end is the optimum. The routes are counted along the
+    // tight edges in order of cost from the start.
+    fn best_paths(&self, pos: Point, dir: Dir) -> BestPaths {
+        let end = *self
+            .map
+            .iter()
+            .find(|(_, &t)| t == Tile::End)
+            .expect("No end")
+            .0;
+        let ends: Vec<State> = [Dir::Left, Dir::Right, Dir::Up, Dir::Down]
+            .iter()
+            .map(|&d| (end, d))
+            .collect();
+
+        let from_start = self.dijkstra(&[(pos, dir)], false);
+        let to_end = self.dijkstra(&ends, true);
+        let cost = ends
+            .iter()
+            .filter_map(|s| from_start.get(s))
+            .copied()
+            .min()
+            .expect("No path to end");
+
+        let mut on_best: Vec<(isize, State)> = from_start
+            .iter()
+            .filter(|(s, &c)| to_end.get(s).is_some_and(|&r| c + r == cost))
+            .map(|(&s, &c)| (c, s))
+            .collect();
+        on_best.sort();
+
+        let mut count: HashMap<State, BigUint> = HashMap::new();
+        count.insert((pos, dir), BigUint::from(1));
+        for &(c, (p, d)) in &on_best {
+            let ways: BigUint = self
+                .moves_into(p, d)
+                .into_iter()
+                .filter(|&(prev, step)| from_start.get(&prev) == Some(&(c - step)))
+                .filter_map(|(prev, _)| count.get(&prev))
+                .fold(BigUint::zero(), |acc, w| &acc + w);
+            if !ways.is_zero() {
+                count.insert((p, d), ways);
+            }
+        }
 
-        tiles_on_best_paths.len() as isize
+        BestPaths {
+            cost,
+            tiles: on_best.iter().map(|&(_, (p, _))| p).collect(),
+            count: ends
+                .iter()
+                .filter(|s| from_start.get(s) == Some(&cost))
+                .filter_map(|s| count.get(s))
+                .fold(BigUint::zero(), |acc, w| &acc + w),
+        }
     }
 
-    fn print_map(&self, path: &HashSet<Point>) {
+    fn print_map(&self, overlay: &HashMap<Point, char>) {
         let mut min_i = isize::MAX;
         let mut max_i = isize::MIN;
         let mut min_j = isize::MAX;
@@ -219,17 +402,247 @@ impl Map {
                     Tile::Start => 'S',
                     Tile::End => 'E',
                 };
-                if path.contains(&point) {
-                    print!("O");
-                } else {
-                    print!("{}", c);
-                }
+                print!("{}", overlay.get(&point).unwrap_or(&c));
             }
             println!();
         }
     }
 }
 
+// The maze as `k_shortest_paths` searches it. Every move turns in place
+// as cheaply as possible to face the next tile and steps onto it, so two
+// routes differ exactly when their tiles do. States (tile, facing) are
+// numbered (i * width + j) * 4 + dir, and everything per state is a dense
+// array.
+struct RouteGraph<'a> {
+    map: &'a Map,
+    width: usize,
+    // turn[from][to]: the cheapest turning in place between two facings
+    turn: [[isize; 4]; 4],
+    // cheapest cost from each state to the end with nothing banned, which
+    // can only underestimate once states or moves are banned
+    to_end: Vec<Option<isize>>,
+}
+
+const DIRS: [Dir; 4] = [Dir::Left, Dir::Right, Dir::Up, Dir::Down];
+
+impl<'a> RouteGraph<'a> {
+    fn new(map: &'a Map) -> Self {
+        let height = map.map.keys().map(|p| p.i).max().unwrap() as usize + 1;
+        let width = map.map.keys().map(|p| p.j).max().unwrap() as usize + 1;
+
+        // a few rounds of relaxing single turns is enough for 4 facings
+        let costs = &map.costs;
+        let mut turn = [[isize::MAX; 4]; 4];
+        for (d, row) in turn.iter_mut().enumerate() {
+            row[d] = 0;
+            for _ in 0..3 {
+                for (k, &dir) in DIRS.iter().enumerate() {
+                    if row[k] == isize::MAX {
+                        continue;
+                    }
+                    let mut single = vec![
+                        (dir.left90(), costs.turn_left),
+                        (dir.right90(), costs.turn_right),
+                    ];
+                    if let Some(c) = costs.u_turn {
+                        single.push((dir.reverse(), c));
+                    }
+                    for (to, c) in single {
+                        let to = to.index();
+                        row[to] = row[to].min(row[k] + c);
+                    }
+                }
+            }
+        }
+
+        let mut graph = RouteGraph {
+            map,
+            width,
+            turn,
+            to_end: vec![],
+        };
+        graph.to_end = graph.costs_to_end(height * width * 4);
+        graph
+    }
+
+    fn state(&self, (pos, dir): State) -> usize {
+        (pos.i as usize * self.width + pos.j as usize) * 4 + dir.index()
+    }
+
+    fn unpack(&self, s: usize) -> State {
+        let tile = s / 4;
+        let pos = Point {
+            i: (tile / self.width) as isize,
+            j: (tile % self.width) as isize,
+        };
+        (pos, DIRS[s % 4])
+    }
+
+    // Turn to face each neighbour that can be stepped onto, then step
+    fn next(&self, s: usize) -> impl Iterator<Item = (usize, isize)> + '_ {
+        let (pos, dir) = self.unpack(s);
+        DIRS.iter().filter_map(move |&d| {
+            let q = pos + d.as_point();
+            match self.map.get(q) {
+                Tile::Wall | Tile::Start => None,
+                tile => Some((
+                    self.state((q, d)),
+                    self.turn[dir.index()][d.index()] + self.map.costs.forward_onto(tile),
+                )),
+            }
+        })
+    }
+
+    // Moves into state s: the reverse of `next`
+    fn prev(&self, s: usize) -> impl Iterator<Item = (usize, isize)> + '_ {
+        let (pos, dir) = self.unpack(s);
+        let back = pos
+            + Point {
+                i: -dir.as_point().i,
+                j: -dir.as_point().j,
+            };
+        let enter = match self.map.get(pos) {
+            Tile::Wall | Tile::Start => None,
+            tile => Some(self.map.costs.forward_onto(tile)),
+        };
+        DIRS.iter().filter_map(move |&d| {
+            let c = enter?;
+            if self.map.get(back) == Tile::Wall {
+                return None;
+            }
+            Some((self.state((back, d)), self.turn[d.index()][dir.index()] + c))
+        })
+    }
+
+    fn costs_to_end(&self, n: usize) -> Vec<Option<isize>> {
+        let mut dist = vec![None; n];
+        let mut heap = BinaryHeap::new();
+        for (p, _) in self.map.map.iter().filter(|(_, &t)| t == Tile::End) {
+            for &d in &DIRS {
+                let s = self.state((*p, d));
+                dist[s] = Some(0);
+                heap.push(Reverse((0, s)));
+            }
+        }
+        while let Some(Reverse((cost, s))) = heap.pop() {
+            if dist[s].is_some_and(|best| best < cost) {
+                continue;
+            }
+            for (p, c) in self.prev(s) {
+                if dist[p].is_none_or(|best| best > cost + c) {
+                    dist[p] = Some(cost + c);
+                    heap.push(Reverse((cost + c, p)));
+                }
+            }
+        }
+        dist
+    }
+
+    // Cheapest route from `from` to the end that avoids the banned states
+    // and moves, as its cost and every state along it. A* with `to_end`
+    // as the estimate, which bans only make more optimistic.
+    fn shortest_route(
+        &self,
+        from: usize,
+        banned_states: &[bool],
+        banned_moves: &HashSet<(usize, usize)>,
+    ) -> Option<(isize, Vec<usize>)> {
+        let mut dist = vec![isize::MAX; self.to_end.len()];
+        let mut prev = vec![usize::MAX; self.to_end.len()];
+        dist[from] = 0;
+        let mut heap = BinaryHeap::from([Reverse((self.to_end[from]?, from))]);
+
+        while let Some(Reverse((estimate, s))) = heap.pop() {
+            let cost = dist[s];
+            if estimate > cost + self.to_end[s].unwrap() {
+                continue;
+            }
+            if self.map.get(self.unpack(s).0) == Tile::End {
+                let mut route = vec![s];
+                while prev[*route.last().unwrap()] != usize::MAX {
+                    route.push(prev[*route.last().unwrap()]);
+                }
+                route.reverse();
+                return Some((cost, route));
+            }
+            for (next, c) in self.next(s) {
+                if banned_states[next] || banned_moves.contains(&(s, next)) {
+                    continue;
+                }
+                let Some(h) = self.to_end[next] else {
+                    continue;
+                };
+                if cost + c < dist[next] {
+                    dist[next] = cost + c;
+                    prev[next] = s;
+                    heap.push(Reverse((cost + c + h, next)));
+                }
+            }
+        }
+        None
+    }
+
+    fn route_cost(&self, route: &[usize]) -> isize {
+        route
+            .windows(2)
+            .map(|w| self.next(w[0]).find(|&(s, _)| s == w[1]).unwrap().1)
+            .sum()
+    }
+
+    // Yen's algorithm: the k cheapest routes from the start to the end
+    // through different sequences of tiles, cheapest first
+    fn k_shortest_paths(&self, dir: Dir, k: usize) -> Vec<(isize, Vec<State>)> {
+        let n = self.to_end.len();
+        let mut found: Vec<(isize, Vec<usize>)> = vec![];
+        let start = self.state((self.map.start, dir));
+        let Some(first) = self.shortest_route(start, &vec![false; n], &HashSet::new()) else {
+            return vec![];
+        };
+        found.push(first);
+
+        let mut candidates = BinaryHeap::new();
+        let mut seen: HashSet<Vec<usize>> = HashSet::from([found[0].1.clone()]);
+        let mut banned_states = vec![false; n];
+        while found.len() < k {
+            let last = found.last().unwrap().1.clone();
+            for i in 0..last.len() - 1 {
+                // branch off the last route at its i-th state
+                let root = &last[..=i];
+                let banned_moves: HashSet<(usize, usize)> = found
+                    .iter()
+                    .filter(|(_, r)| r.len() > i + 1 && r[..=i] == *root)
+                    .map(|(_, r)| (r[i], r[i + 1]))
+                    .collect();
+                for &s in &root[..i] {
+                    banned_states[s] = true;
+                }
+                let spur = self.shortest_route(last[i], &banned_states, &banned_moves);
+                for &s in &root[..i] {
+                    banned_states[s] = false;
+                }
+
+                let Some((spur_cost, spur)) = spur else {
+                    continue;
+                };
+                let mut route = root[..i].to_vec();
+                route.extend(spur);
+                if seen.insert(route.clone()) {
+                    candidates.push(Reverse((self.route_cost(root) + spur_cost, route)));
+                }
+            }
+            match candidates.pop() {
+                Some(Reverse(best)) => found.push(best),
+                None => break,
+            }
+        }
+        found
+            .into_iter()
+            .map(|(cost, route)| (cost, route.into_iter().map(|s| self.unpack(s)).collect()))
+            .collect()
+    }
+}
+
 fn part1(txt: &str) -> isize {
     let map = Map::from_str(txt);
 
@@ -239,7 +652,7 @@ fn part1(txt: &str) -> isize {
 fn part2(txt: &str) -> isize {
     let map = Map::from_str(txt);
 
-    map.count_all_tiles_on_best_paths(map.start, Dir::Right)
+    map.best_paths(map.start, Dir::Right).tiles.len() as isize
 }
 
 fn main() {
@@ -250,6 +663,32 @@ fn main() {
     // let path = String::from(root) + "/src/" + day_x + "/test1.txt";
     let txt = fs::read_to_string(path).unwrap();
 
+    let args: Vec<String> = env::args().collect();
+    // `cargo run --bin day16 -- paths K [uturn]`: the K cheapest routes,
+    // optionally letting the reindeer turn around for the cost of two turns
+    if args.get(1).map(|a| a.as_str()) == Some("paths") {
+        let mut costs = CostModel::default();
+        if args.get(3).map(|a| a.as_str()) == Some("uturn") {
+            costs.u_turn = Some(costs.turn_left + costs.turn_right);
+        }
+        let map = Map::from_str(&txt).with_costs(costs);
+        let graph = RouteGraph::new(&map);
+        for (score, route) in graph.k_shortest_paths(Dir::Right, args[2].parse().unwrap()) {
+            let turns = route.windows(2).filter(|w| w[0].1 != w[1].1).count();
+            println!("{} ({} tiles, {} turns)", score, route.len(), turns);
+        }
+        return;
+    }
+    // `cargo run --bin day16 -- show`: draw the tiles on the best paths
+    // and count the paths
+    if args.get(1).map(|a| a.as_str()) == Some("show") {
+        let map = Map::from_str(&txt);
+        let best = map.best_paths(map.start, Dir::Right);
+        map.print_map(&best.overlay());
+        println!("cost: {}, best paths: {}", best.cost, best.count);
+        return;
+    }
+
     println!("This is {}", day_x);
     println!("Part 1: {:?}", part1(&txt));
     println!("Part 2: {:?}", part2(&txt));
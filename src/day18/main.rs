@@ -1,8 +1,78 @@
+#![allow(non_snake_case)]
+
 use std::{
-    collections::{HashMap, VecDeque},
-    fs,
+    collections::{HashMap, HashSet, VecDeque},
+    env, fs,
 };
 
+// The memory space to search: an N x N grid, the corner-to-corner walk,
+// and how many bytes have fallen before we start looking for a cut
+#[derive(Debug, Clone, Copy)]
+struct Space {
+    N: isize,
+    start: Point,
+    end: Point,
+    initial: usize,
+}
+
+impl Space {
+    fn new(N: isize, initial: usize) -> Self {
+        Space {
+            N,
+            start: Point { i: 0, j: 0 },
+            end: Point { i: N - 1, j: N - 1 },
+            initial,
+        }
+    }
+
+    fn index(&self, p: Point) -> usize {
+        (p.i * self.N + p.j) as usize
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CutSearch {
+    // bisect on the number of fallen bytes, with a BFS per probe
+    Bisect,
+    // start with every byte fallen and take them back out from the end,
+    // joining up free cells with a union-find, until start meets end
+    ReverseUnionFind,
+}
+
+struct UnionFind {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+}
+
+impl UnionFind {
+    fn new(n: usize) -> Self {
+        UnionFind {
+            parent: (0..n).collect(),
+            size: vec![1; n],
+        }
+    }
+
+    fn find(&mut self, mut x: usize) -> usize {
+        while self.parent[x] != x {
+            self.parent[x] = self.parent[self.parent[x]];
+            x = self.parent[x];
+        }
+        x
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+        if self.size[a] < self.size[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        self.parent[b] = a;
+        self.size[a] += self.size[b];
+    }
+}
+
 #[derive(Debug)]
 struct Map {
     grid: HashMap<Point, Tile>,
@@ -118,6 +188,69 @@ impl Map {
 
         None
     }
+
+    // Like `shortest_dist`, but `falling[k]` only lands at time k, on top
+    // of whatever is already in the map. Each step takes one unit of time,
+    // and the walker can't enter a cell at or after the time a byte lands
+    // there. With `wait`, standing still for a step is also allowed, as
+    // long as nothing lands on the walker.
+    //
+    // This is a BFS over (point, time). Once every byte has fallen the
+    // map is static, so times past that are folded together, which is what
+    // makes it terminate when the end is unreachable.
+    //
+    // Returns the arrival time and the path, one point per time step.
+    fn shortest_timed(
+        &self,
+        falling: &[Point],
+        start: Point,
+        end: Point,
+        wait: bool,
+    ) -> Option<(usize, Vec<Point>)> {
+        let mut lands: HashMap<Point, usize> = HashMap::new();
+        for (k, &p) in falling.iter().enumerate() {
+            lands.entry(p).or_insert(k);
+        }
+        let free_at = |p: Point, t: usize| {
+            self.get(p) == Some(Tile::Empty) && lands.get(&p).is_none_or(|&k| t < k)
+        };
+        let settled = falling.len();
+
+        if !free_at(start, 0) {
+            return None;
+        }
+        let mut prev: HashMap<(Point, usize), (Point, usize)> = HashMap::new();
+        let mut seen = HashSet::from([(start, 0)]);
+        let mut queue = VecDeque::from([(start, 0)]);
+        while let Some((p, t)) = queue.pop_front() {
+            if p == end {
+                let mut path = vec![p];
+                let mut state = (p, t);
+                while let Some(&before) = prev.get(&state) {
+                    path.push(before.0);
+                    state = before;
+                }
+                path.reverse();
+                return Some((t, path));
+            }
+
+            let mut nexts = vec![];
+            for dir in [Dir::Left, Dir::Right, Dir::Up, Dir::Down] {
+                nexts.push(p + dir);
+            }
+            if wait {
+                nexts.push(p);
+            }
+            for next in nexts {
+                if !free_at(next, t + 1) || !seen.insert((next, (t + 1).min(settled))) {
+                    continue;
+                }
+                prev.insert((next, t + 1), (p, t));
+                queue.push_back((next, t + 1));
+            }
+        }
+        None
+    }
 }
 
 fn get_points(txt: &str) -> Vec<Point> {
@@ -131,34 +264,121 @@ fn get_points(txt: &str) -> Vec<Point> {
         .collect()
 }
 
+fn blocked_after(bytes: &[Point], k: usize, space: Space) -> bool {
+    let map = Map::from_points(&bytes[..k].to_vec(), space.N);
+    map.shortest_dist(space.start, space.end).is_none()
+}
+
+// The first byte, at index `space.initial` or later, after which there's
+// no path from start to end. None if the path is never cut, or is already
+// cut by the initial bytes.
+fn first_cut(bytes: &[Point], space: Space, method: CutSearch) -> Option<(usize, Point)> {
+    if space.initial > bytes.len() || blocked_after(bytes, space.initial, space) {
+        return None;
+    }
+    match method {
+        CutSearch::Bisect => {
+            if !blocked_after(bytes, bytes.len(), space) {
+                return None;
+            }
+            // open after lo bytes, blocked after hi
+            let (mut lo, mut hi) = (space.initial, bytes.len());
+            while hi - lo > 1 {
+                let mid = (lo + hi) / 2;
+                if blocked_after(bytes, mid, space) {
+                    hi = mid;
+                } else {
+                    lo = mid;
+                }
+            }
+            Some((hi - 1, bytes[hi - 1]))
+        }
+        CutSearch::ReverseUnionFind => {
+            let n = (space.N * space.N) as usize;
+            // the first byte to land on each cell; only removing that one
+            // frees the cell
+            let mut first = vec![usize::MAX; n];
+            for (k, &p) in bytes.iter().enumerate().rev() {
+                first[space.index(p)] = k;
+            }
+
+            let map = Map::from_points(&bytes.to_vec(), space.N);
+            let mut free: Vec<bool> = (0..n).map(|c| first[c] == usize::MAX).collect();
+            let mut uf = UnionFind::new(n);
+            for i in 0..space.N {
+                for j in 0..space.N {
+                    let p = Point { i, j };
+                    for q in map.moves(p) {
+                        if free[space.index(p)] {
+                            uf.union(space.index(p), space.index(q));
+                        }
+                    }
+                }
+            }
+
+            let (s, e) = (space.index(space.start), space.index(space.end));
+            if free[s] && free[e] && uf.find(s) == uf.find(e) {
+                return None;
+            }
+            for k in (space.initial..bytes.len()).rev() {
+                let p = bytes[k];
+                let c = space.index(p);
+                if first[c] != k {
+                    continue;
+                }
+                free[c] = true;
+                for dir in [Dir::Left, Dir::Right, Dir::Up, Dir::Down] {
+                    let q = p + dir;
+                    if map.get(q).is_some() && free[space.index(q)] {
+                        uf.union(c, space.index(q));
+                    }
+                }
+                if free[s] && free[e] && uf.find(s) == uf.find(e) {
+                    return Some((k, p));
+                }
+            }
+            None
+        }
+    }
+}
+
 fn part1(txt: &str) -> isize {
     let bytes = get_points(txt);
-    let map = Map::from_points(&bytes.into_iter().take(1024).collect(), 71);
-    let start = Point { i: 0, j: 0 };
-    let end = Point {
-        i: map.N - 1,
-        j: map.N - 1,
-    };
-    map.shortest_dist(start, end).unwrap()
+    let space = Space::new(71, 1024);
+    let map = Map::from_points(&bytes[..space.initial].to_vec(), space.N);
+    map.shortest_dist(space.start, space.end).unwrap()
 }
 
 fn part2(txt: &str) -> String {
     let bytes = get_points(txt);
-    let mut map = Map::from_points(&bytes.clone().into_iter().take(1024).collect(), 71);
-    let start = Point { i: 0, j: 0 };
-    let end = Point {
-        i: map.N - 1,
-        j: map.N - 1,
-    };
+    let space = Space::new(71, 1024);
+
+    let (_, point) =
+        first_cut(&bytes, space, CutSearch::ReverseUnionFind).expect("No solution found");
+    format!("{},{}", point.i, point.j)
+}
+
+fn print_cut(txt: &str, method: CutSearch) {
+    let bytes = get_points(txt);
+    match first_cut(&bytes, Space::new(71, 1024), method) {
+        Some((k, p)) => println!("Byte {} at {},{} cuts the path ({:?})", k, p.i, p.j, method),
+        None => println!("The path is never cut"),
+    }
+}
 
-    for point in bytes.into_iter().skip(1024) {
-        map.grid.insert(point, Tile::Corrupted);
+fn print_timed(txt: &str, wait: bool) {
+    let bytes = get_points(txt);
+    let space = Space::new(71, 1024);
+    let map = Map::from_points(&vec![], space.N);
 
-        if map.shortest_dist(start, end).is_none() {
-            return format!("{},{}", point.i, point.j);
+    match map.shortest_timed(&bytes, space.start, space.end, wait) {
+        Some((arrival, path)) => {
+            println!("Arrives at t = {}", arrival);
+            let path: Vec<String> = path.iter().map(|p| format!("{},{}", p.i, p.j)).collect();
+            println!("{}", path.join(" "));
         }
+        None => println!("Can't get out"),
     }
-    panic!("No solution found");
 }
 
 fn main() {
@@ -169,7 +389,74 @@ fn main() {
     // let path = String::from(root) + "/src/" + day_x + "/test1.txt";
     let txt = fs::read_to_string(path).unwrap();
 
+    // `cargo run --bin day18 -- timed [wait]`: bytes fall while you walk
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(|a| a.as_str()) == Some("timed") {
+        print_timed(&txt, args.get(2).map(|a| a.as_str()) == Some("wait"));
+        return;
+    }
+    // `cargo run --bin day18 -- cut [bisect]`
+    if args.get(1).map(|a| a.as_str()) == Some("cut") {
+        let method = match args.get(2).map(|a| a.as_str()) {
+            Some("bisect") => CutSearch::Bisect,
+            _ => CutSearch::ReverseUnionFind,
+        };
+        print_cut(&txt, method);
+        return;
+    }
+
     println!("This is {}", day_x);
     println!("Part 1: {:?}", part1(&txt));
     println!("Part 2: {:?}", part2(&txt));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+5,4
+4,2
+4,5
+3,0
+2,1
+6,3
+2,4
+1,5
+0,6
+3,3
+2,6
+5,1
+1,2
+5,5
+2,5
+6,5
+1,4
+0,4
+6,4
+1,1
+6,1
+1,0
+0,5
+1,6
+2,0
+";
+
+    #[test]
+    fn cut_searches_agree() {
+        let bytes = get_points(EXAMPLE);
+        let space = Space::new(7, 12);
+        let cut = first_cut(&bytes, space, CutSearch::ReverseUnionFind);
+        assert_eq!(cut, Some((20, Point { i: 6, j: 1 })));
+        assert_eq!(cut, first_cut(&bytes, space, CutSearch::Bisect));
+    }
+
+    #[test]
+    fn cut_searches_agree_when_never_cut() {
+        let bytes = get_points(EXAMPLE);
+        let space = Space::new(7, 12);
+        let few = &bytes[..20];
+        assert_eq!(first_cut(few, space, CutSearch::ReverseUnionFind), None);
+        assert_eq!(first_cut(few, space, CutSearch::Bisect), None);
+    }
+}
@@ -1,4 +1,4 @@
-use std::{collections::HashMap, fs};
+use std::{collections::HashMap, env, fs};
 
 fn parse(txt: &str) -> [Vec<&str>; 2] {
     let mut parts = txt.split("\n\n");
@@ -7,36 +7,309 @@ fn parse(txt: &str) -> [Vec<&str>; 2] {
     [towels, patterns]
 }
 
-type Memo<'a> = HashMap<&'a str, isize>;
+// Trie over the towels' stripes. `towels[node]` are the indices of the
+// towels that end at that node; a repeated towel is listed once per copy,
+// and each copy counts as its own arrangement.
+struct Towels<'a> {
+    names: Vec<&'a str>,
+    children: Vec<HashMap<u8, usize>>,
+    towels: Vec<Vec<usize>>,
+}
+
+impl<'a> Towels<'a> {
+    fn new(names: &[&'a str]) -> Self {
+        let mut trie = Towels {
+            names: names.to_vec(),
+            children: vec![HashMap::new()],
+            towels: vec![vec![]],
+        };
+        for (t, name) in names.iter().enumerate() {
+            let mut node = 0;
+            for b in name.bytes() {
+                node = match trie.children[node].get(&b) {
+                    Some(&next) => next,
+                    None => {
+                        trie.children.push(HashMap::new());
+                        trie.towels.push(vec![]);
+                        let next = trie.children.len() - 1;
+                        trie.children[node].insert(b, next);
+                        next
+                    }
+                };
+            }
+            trie.towels[node].push(t);
+        }
+        trie
+    }
+
+    // Every towel that matches `pattern` starting at `pos`, as
+    // (towel index, length), by walking down the trie
+    fn matches_at(&self, pattern: &[u8], pos: usize) -> Vec<(usize, usize)> {
+        let mut matches = vec![];
+        let mut node = 0;
+        for (k, b) in pattern[pos..].iter().enumerate() {
+            match self.children[node].get(b) {
+                Some(&next) => node = next,
+                None => break,
+            }
+            matches.extend(self.towels[node].iter().map(|&t| (t, k + 1)));
+        }
+        matches
+    }
+}
 
-fn search<'a>(pattern: &'a str, towels: &Vec<&'a str>, memo: &mut Memo<'a>) -> isize {
-    if let Some(&i) = memo.get(pattern) {
-        return i;
+// Ways to arrange the towels into `pattern[i..]`, for every i, built
+// bottom-up from the end. None if the count doesn't fit in a u128.
+fn search(pattern: &str, towels: &Towels) -> Vec<Option<u128>> {
+    let bytes = pattern.as_bytes();
+    let mut ways = vec![Some(0); bytes.len() + 1];
+    ways[bytes.len()] = Some(1);
+    for i in (0..bytes.len()).rev() {
+        ways[i] = towels
+            .matches_at(bytes, i)
+            .iter()
+            .try_fold(0u128, |acc, &(_, len)| acc.checked_add(ways[i + len]?));
     }
-    if pattern.len() == 0 {
-        return 1;
+    ways
+}
+
+fn count(pattern: &str, towels: &Towels) -> Option<u128> {
+    search(pattern, towels)[0]
+}
+
+// The towels, in order, for one way to make `pattern`
+fn decomposition<'a>(pattern: &str, towels: &Towels<'a>) -> Option<Vec<&'a str>> {
+    let ways = search(pattern, towels);
+    let bytes = pattern.as_bytes();
+    let mut pos = 0;
+    let mut used = vec![];
+    while pos < bytes.len() {
+        let (t, len) = towels
+            .matches_at(bytes, pos)
+            .into_iter()
+            .find(|&(_, len)| ways[pos + len] != Some(0))?;
+        used.push(towels.names[t]);
+        pos += len;
     }
+    Some(used)
+}
 
-    let total = towels.into_iter().filter(|&&t| pattern.starts_with(t)).map(|&t| {
-        // println!("{}: {}", t, pattern);
-        search(&pattern[t.len()..], towels, memo)
-    }).sum();
-    memo.insert(pattern, total);
-    total
+// Lazily yields every way to make the pattern. Only towels that leave a
+// buildable remainder are tried, so there's no backtracking into dead ends.
+struct Decompositions<'t, 'a> {
+    towels: &'t Towels<'a>,
+    // viable[pos]: the (towel, length) choices at pos
+    viable: Vec<Vec<(usize, usize)>>,
+    // the choice index taken at each step so far
+    stack: Vec<usize>,
+    // positions where each step starts, plus where the next one would
+    starts: Vec<usize>,
+    done: bool,
+}
+
+impl<'t, 'a> Decompositions<'t, 'a> {
+    fn new(pattern: &str, towels: &'t Towels<'a>) -> Self {
+        let ways = search(pattern, towels);
+        let bytes = pattern.as_bytes();
+        let viable = (0..bytes.len())
+            .map(|pos| {
+                towels
+                    .matches_at(bytes, pos)
+                    .into_iter()
+                    .filter(|&(_, len)| ways[pos + len] != Some(0))
+                    .collect()
+            })
+            .collect();
+        Decompositions {
+            towels,
+            viable,
+            stack: vec![],
+            starts: vec![0],
+            done: ways[0] == Some(0),
+        }
+    }
+
+    // Take first choices from the current position until the end
+    fn descend(&mut self) {
+        loop {
+            let pos = *self.starts.last().unwrap();
+            if pos == self.viable.len() {
+                return;
+            }
+            let (_, len) = self.viable[pos][0];
+            self.stack.push(0);
+            self.starts.push(pos + len);
+        }
+    }
+
+    // Move to the next choice at the deepest step that has one
+    fn advance(&mut self) -> bool {
+        while let Some(choice) = self.stack.pop() {
+            self.starts.pop();
+            let pos = *self.starts.last().unwrap();
+            if choice + 1 < self.viable[pos].len() {
+                let (_, len) = self.viable[pos][choice + 1];
+                self.stack.push(choice + 1);
+                self.starts.push(pos + len);
+                return true;
+            }
+        }
+        false
+    }
+}
+
+impl<'a> Iterator for Decompositions<'_, 'a> {
+    type Item = Vec<&'a str>;
+
+    fn next(&mut self) -> Option<Vec<&'a str>> {
+        if self.done {
+            return None;
+        }
+        self.descend();
+        let used = self
+            .stack
+            .iter()
+            .zip(&self.starts)
+            .map(|(&choice, &pos)| self.towels.names[self.viable[pos][choice].0])
+            .collect();
+        self.done = !self.advance();
+        Some(used)
+    }
+}
+
+// Towels that can be made from other towels. Each of those needs at
+// least two shorter pieces (or is a repeat of an earlier towel), so the
+// rest, the irreducible towels, generate every design the full set can,
+// and any generating subset must contain all of them.
+fn redundant_towels(names: &[&str]) -> Vec<bool> {
+    let towels = Towels::new(names);
+    names
+        .iter()
+        .enumerate()
+        .map(|(t, name)| {
+            // each copy of the towel is one arrangement; anything more
+            // means other towels can make it too
+            let copies = names.iter().filter(|&n| n == name).count() as u128;
+            names[..t].contains(name) || count(name, &towels) != Some(copies)
+        })
+        .collect()
+}
+
+// Length of the longest prefix of `pattern` that the towels can make
+fn buildable_prefix(pattern: &str, towels: &Towels) -> usize {
+    let bytes = pattern.as_bytes();
+    let mut reached = vec![false; bytes.len() + 1];
+    reached[0] = true;
+    let mut longest = 0;
+    for pos in 0..bytes.len() {
+        if !reached[pos] {
+            continue;
+        }
+        for (_, len) in towels.matches_at(bytes, pos) {
+            reached[pos + len] = true;
+            longest = longest.max(pos + len);
+        }
+    }
+    longest
+}
+
+#[derive(Debug)]
+struct DesignReport<'a> {
+    pattern: &'a str,
+    // None if it overflows a u128
+    arrangements: Option<u128>,
+    // for designs that can't be made, how far the towels get
+    longest_prefix: Option<usize>,
+}
+
+fn analyze<'a>(patterns: &[&'a str], towels: &Towels) -> Vec<DesignReport<'a>> {
+    patterns
+        .iter()
+        .map(|&pattern| {
+            let arrangements = count(pattern, towels);
+            DesignReport {
+                pattern,
+                arrangements,
+                longest_prefix: (arrangements == Some(0))
+                    .then(|| buildable_prefix(pattern, towels)),
+            }
+        })
+        .collect()
 }
 
 fn part1(txt: &str) -> usize {
     let [towels, patterns] = parse(txt);
+    let towels = Towels::new(&towels);
 
-    patterns.into_iter().filter(|&p| search(p, &towels, &mut Memo::new()) > 0).count()
+    patterns
+        .into_iter()
+        .filter(|&p| count(p, &towels) != Some(0))
+        .count()
 }
 
-fn part2(txt: &str) -> isize {
+fn part2(txt: &str) -> u128 {
     let [towels, patterns] = parse(txt);
+    let towels = Towels::new(&towels);
+
+    patterns
+        .into_iter()
+        .map(|p| count(p, &towels).expect("arrangement count overflows u128"))
+        .try_fold(0u128, |acc, c| acc.checked_add(c))
+        .expect("arrangement count overflows u128")
+}
+
+fn print_arrangements(txt: &str, pattern: &str, limit: usize) {
+    let [towels, _] = parse(txt);
+    let towels = Towels::new(&towels);
 
-    patterns.into_iter().map(|p| {
-        search(p, &towels, &mut Memo::new())}
-    ).sum()
+    match count(pattern, &towels) {
+        Some(n) => println!("{}: {} arrangements", pattern, n),
+        None => println!("{}: more than {} arrangements", pattern, u128::MAX),
+    }
+    if let Some(used) = decomposition(pattern, &towels) {
+        println!("e.g. {}", used.join(" "));
+    }
+    for used in Decompositions::new(pattern, &towels).take(limit) {
+        println!("  {}", used.join(" "));
+    }
+}
+
+fn print_analysis(txt: &str) {
+    let [names, patterns] = parse(txt);
+
+    let redundant = redundant_towels(&names);
+    let minimal: Vec<&str> = names
+        .iter()
+        .zip(&redundant)
+        .filter(|(_, &r)| !r)
+        .map(|(&n, _)| n)
+        .collect();
+    println!(
+        "{} of {} towels are redundant",
+        names.len() - minimal.len(),
+        names.len()
+    );
+    println!("Minimal set: {}", minimal.join(", "));
+
+    // the minimal set makes the same designs, in fewer ways
+    let towels = Towels::new(&names);
+    let minimal_towels = Towels::new(&minimal);
+    for report in analyze(&patterns, &towels) {
+        match (report.arrangements, report.longest_prefix) {
+            (_, Some(prefix)) => println!(
+                "{}: impossible, longest buildable prefix {:?}",
+                report.pattern,
+                &report.pattern[..prefix]
+            ),
+            (Some(n), None) => println!(
+                "{}: {} arrangements, {:?} from the minimal set",
+                report.pattern,
+                n,
+                count(report.pattern, &minimal_towels)
+            ),
+            (None, None) => println!("{}: more than {} arrangements", report.pattern, u128::MAX),
+        }
+    }
 }
 
 fn main() {
@@ -47,6 +320,19 @@ fn main() {
     // let path = String::from(root) + "/src/" + day_x + "/test1.txt";
     let txt = fs::read_to_string(path).unwrap();
 
+    // `cargo run --bin day19 -- show DESIGN [LIMIT]`
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(|a| a.as_str()) == Some("show") {
+        let limit = args.get(3).map_or(20, |a| a.parse().unwrap());
+        print_arrangements(&txt, &args[2], limit);
+        return;
+    }
+    // `cargo run --bin day19 -- analyze`
+    if args.get(1).map(|a| a.as_str()) == Some("analyze") {
+        print_analysis(&txt);
+        return;
+    }
+
     println!("This is {}", day_x);
     println!("Part 1: {:?}", part1(&txt));
     println!("Part 2: {:?}", part2(&txt));
@@ -1,17 +1,36 @@
 #![allow(non_snake_case)]
 
-use std::cmp::Reverse;
-use std::collections::{BinaryHeap, HashSet};
-use std::{collections::HashMap, fs};
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::{collections::HashMap, env, fs};
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 struct Map {
     map: HashMap<Point, Tile>,
-    cost_map: HashMap<Point, isize>,
+    // distances from the start and to the end for every tile, row-major;
+    // None for walls and tiles that can't reach (or be reached)
+    from_start: Vec<Option<isize>>,
+    to_end: Vec<Option<isize>>,
     start: Point,
+    end: Point,
     N: Point,
 }
 
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+struct CheatRule {
+    max_jump: isize,
+    min_save: isize,
+    // only cheat through walls, never over other track
+    walls_only: bool,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+struct Cheat {
+    start: Point,
+    end: Point,
+    saving: isize,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
 enum Tile {
     Empty,
@@ -80,6 +99,7 @@ impl Map {
     fn from_str(txt: &str) -> Self {
         let mut map = HashMap::new();
         let mut start = Point { i: 0, j: 0 };
+        let mut end = Point { i: 0, j: 0 };
         let mut N = Point { i: 0, j: 0 };
         for (i, line) in txt.lines().enumerate() {
             for (j, c) in line.chars().enumerate() {
@@ -94,7 +114,10 @@ impl Map {
                         start = point;
                         Tile::Start
                     }
-                    'E' => Tile::End,
+                    'E' => {
+                        end = point;
+                        Tile::End
+                    }
                     _ => panic!("Invalid tile: {}", c),
                 };
                 map.insert(point, tile);
@@ -105,134 +128,213 @@ impl Map {
         let mut ret = Self {
             map,
             start,
+            end,
             N,
-            cost_map: HashMap::new(),
+            from_start: vec![],
+            to_end: vec![],
         };
-        ret.fill_cost_map();
+        ret.from_start = ret.bfs(start);
+        ret.to_end = ret.bfs(end);
         ret
     }
 
-    fn fill_cost_map(&mut self) {
-        let positions: Vec<Point> = self
-            .map
-            .iter()
-            .filter(|(_, &tile)| tile != Tile::Wall)
-            .map(|(&pos, _)| pos)
-            .collect();
-        for pos in positions {
-            self.cost_map.insert(pos, self.cost_to_end(pos));
+    fn index(&self, pos: Point) -> Option<usize> {
+        if pos.i < 0 || pos.j < 0 || pos.i >= self.N.i || pos.j >= self.N.j {
+            None
+        } else {
+            Some((pos.i * self.N.j + pos.j) as usize)
         }
     }
 
-    fn get(&self, pos: Point) -> Tile {
-        *self.map.get(&pos).unwrap()
+    fn point(&self, index: usize) -> Point {
+        Point {
+            i: index as isize / self.N.j,
+            j: index as isize % self.N.j,
+        }
     }
 
-    fn is_edge(&self, pos: Point) -> bool {
-        pos.i == 0 || pos.j == 0 || pos.i == self.N.i - 1 || pos.j == self.N.j - 1
+    // distance from `source` to every tile. Moves are reversible, so
+    // this is also the distance from every tile to `source`.
+    fn bfs(&self, source: Point) -> Vec<Option<isize>> {
+        let mut dist = vec![None; (self.N.i * self.N.j) as usize];
+        dist[self.index(source).unwrap()] = Some(0);
+
+        let mut queue = VecDeque::from([source]);
+        while let Some(pos) = queue.pop_front() {
+            let cost = dist[self.index(pos).unwrap()].unwrap();
+            for (dir, step) in self.moves(pos) {
+                let new_pos = self.index(pos + dir).unwrap();
+                if dist[new_pos].is_none() {
+                    dist[new_pos] = Some(cost + step);
+                    queue.push_back(pos + dir);
+                }
+            }
+        }
+        dist
     }
 
-    fn cost_to_end(&self, pos: Point) -> isize {
-        let mut heap = BinaryHeap::new();
-        heap.push(Reverse((0, pos)));
-
-        let mut visited = HashMap::new();
-        visited.insert(pos, 0);
+    // The tile at pos, or None off the map
+    fn get(&self, pos: Point) -> Option<Tile> {
+        self.map.get(&pos).copied()
+    }
 
-        while let Some(Reverse((cost, pos))) = heap.pop() {
-            if self.get(pos) == Tile::End {
-                return cost;
-            }
+    fn is_track(&self, pos: Point) -> bool {
+        self.get(pos).is_some_and(|t| t != Tile::Wall)
+    }
 
-            for (new_dir, new_cost) in self.moves(pos, Some(0)) {
-                let new_pos = pos + new_dir;
-                let new_cost = cost + new_cost;
-                if let Some(&best_cost) = visited.get(&new_pos) {
-                    if new_cost > best_cost {
-                        continue;
-                    }
-                }
-                visited.insert(new_pos, new_cost);
-                heap.push(Reverse((new_cost, new_pos)));
-            }
-        }
+    fn cost_to_end(&self, pos: Point) -> Option<isize> {
+        self.to_end[self.index(pos)?]
+    }
 
-        panic!("No path to end");
+    // length of the honest shortest path, if there is one
+    fn shortest(&self) -> Option<isize> {
+        self.cost_to_end(self.start)
     }
 
-    fn moves(&self, pos: Point, cheat: Option<isize>) -> Vec<(Dir, isize)> {
+    fn moves(&self, pos: Point) -> Vec<(Dir, isize)> {
         let mut moves = vec![];
         for &dir in [Dir::Left, Dir::Right, Dir::Up, Dir::Down].iter() {
-            let new_pos = pos + dir;
-            if self.get(new_pos) != Tile::Wall {
+            if self.is_track(pos + dir) {
                 moves.push((dir, 1));
             }
         }
         moves
     }
 
-    fn count_cheats(&self, min_save: isize, max_jump: isize) -> isize {
-        // For every empty square, calculate if jumping up to max_jump
-        // squares saves more than min_save distance.
-
-        let mut count = 0;
-        for (&pos, &cost) in self.cost_map.iter() {
+    // Where a cheat from `pos` can come out, with the cheat's length.
+    // Normally that's any track tile within `max_jump` (Manhattan); with
+    // `walls_only`, every square passed through on the way must be wall,
+    // so the length is the shortest such route.
+    fn cheat_exits(&self, pos: Point, max_jump: isize, walls_only: bool) -> Vec<(Point, isize)> {
+        if !walls_only {
+            let mut exits = vec![];
             for ijump in -max_jump..=max_jump {
-                for jjump in -max_jump..=max_jump {
-                    let jump_dist = ijump.abs() + jjump.abs();
-                    if jump_dist == 0 || jump_dist > max_jump {
-                        continue;
-                    }
-                    let jump = Point { i: ijump, j: jjump };
-                    let new_pos = pos + jump;
-                    if let Some(new_cost) = self.cost_map.get(&new_pos) {
-                        if cost - (new_cost + jump_dist) >= min_save {
-                            count += 1;
-                        }
+                let jrange = max_jump - ijump.abs();
+                for jjump in -jrange..=jrange {
+                    let new_pos = pos + Point { i: ijump, j: jjump };
+                    if self.is_track(new_pos) {
+                        exits.push((new_pos, ijump.abs() + jjump.abs()));
                     }
                 }
             }
+            return exits;
         }
-        count
-    }
-
-    fn print_map(&self, path: &HashSet<Point>) {
-        for i in 0..=self.N.i {
-            for j in 0..=self.N.j {
-                let point = Point { i, j };
-                let c = match self.get(point) {
-                    Tile::Empty => '.',
-                    Tile::Wall => '#',
-                    Tile::Start => 'S',
-                    Tile::End => 'E',
-                };
-                if path.contains(&point) {
-                    print!("O");
-                } else {
-                    print!("{}", c);
+
+        // BFS through walls only, stepping out onto track at the end
+        let mut exits = HashMap::new();
+        let mut seen = HashSet::from([pos]);
+        let mut queue = VecDeque::from([(pos, 0)]);
+        while let Some((p, d)) = queue.pop_front() {
+            if d == max_jump {
+                continue;
+            }
+            for dir in [Dir::Left, Dir::Right, Dir::Up, Dir::Down] {
+                let next = p + dir;
+                if !seen.insert(next) {
+                    continue;
                 }
+                match self.get(next) {
+                    Some(Tile::Wall) => queue.push_back((next, d + 1)),
+                    Some(_) => {
+                        exits.entry(next).or_insert(d + 1);
+                    }
+                    None => (),
+                }
+            }
+        }
+        exits.into_iter().collect()
+    }
+
+    // Cheats from `pos` that save at least `min_save` over the shortest
+    // honest path, which may differ from the path through `pos`
+    fn cheats_from(&self, pos: Point, rule: CheatRule) -> Vec<Cheat> {
+        let (Some(best), Some(before)) =
+            (self.shortest(), self.from_start[self.index(pos).unwrap()])
+        else {
+            return vec![];
+        };
+        let mut cheats = vec![];
+        for (new_pos, jump) in self.cheat_exits(pos, rule.max_jump, rule.walls_only) {
+            let Some(after) = self.cost_to_end(new_pos) else {
+                continue;
+            };
+            let saving = best - (before + jump + after);
+            if jump > 0 && saving >= rule.min_save {
+                cheats.push(Cheat {
+                    start: pos,
+                    end: new_pos,
+                    saving,
+                });
             }
-            println!();
         }
+        cheats
+    }
+
+    // Every cheat saving at least `min_save`, in parallel over start tiles
+    fn cheats(&self, rule: CheatRule) -> Vec<Cheat> {
+        (0..self.from_start.len())
+            .into_par_iter()
+            .flat_map_iter(|k| self.cheats_from(self.point(k), rule))
+            .collect()
+    }
+
+    fn count_cheats(&self, rule: CheatRule) -> isize {
+        (0..self.from_start.len())
+            .into_par_iter()
+            .map(|k| self.cheats_from(self.point(k), rule).len() as isize)
+            .sum()
+    }
+
+    // saving -> number of cheats with that saving
+    fn cheat_histogram(&self, rule: CheatRule) -> BTreeMap<isize, usize> {
+        let mut hist = BTreeMap::new();
+        for cheat in self.cheats(rule) {
+            *hist.entry(cheat.saving).or_insert(0) += 1;
+        }
+        hist
     }
 }
 
 fn part1(txt: &str) -> isize {
     let map = Map::from_str(txt);
 
-    let orig_cost = map.cost_to_end(map.start);
-    println!("orig_cost: {}", orig_cost);
+    if let Some(orig_cost) = map.shortest() {
+        println!("orig_cost: {}", orig_cost);
+    }
 
-    map.count_cheats(100, 2)
+    map.count_cheats(CheatRule {
+        max_jump: 2,
+        min_save: 100,
+        walls_only: false,
+    })
 }
 
 fn part2(txt: &str) -> isize {
     let map = Map::from_str(txt);
 
-    let orig_cost = map.cost_to_end(map.start);
-    println!("orig_cost: {}", orig_cost);
+    if let Some(orig_cost) = map.shortest() {
+        println!("orig_cost: {}", orig_cost);
+    }
+
+    map.count_cheats(CheatRule {
+        max_jump: 20,
+        min_save: 100,
+        walls_only: false,
+    })
+}
 
-    map.count_cheats(100, 20)
+fn print_histogram(txt: &str, rule: CheatRule) {
+    let map = Map::from_str(txt);
+    for (saving, count) in map.cheat_histogram(rule) {
+        if count == 1 {
+            println!("There is one cheat that saves {} picoseconds.", saving);
+        } else {
+            println!(
+                "There are {} cheats that save {} picoseconds.",
+                count, saving
+            );
+        }
+    }
 }
 
 fn main() {
@@ -243,6 +345,18 @@ fn main() {
     // let path = String::from(root) + "/src/" + day_x + "/test1.txt";
     let txt = fs::read_to_string(path).unwrap();
 
+    // `cargo run --bin day20 -- histogram MAX_JUMP [MIN_SAVE] [walls]`
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(|a| a.as_str()) == Some("histogram") {
+        let rule = CheatRule {
+            max_jump: args[2].parse().unwrap(),
+            min_save: args.get(3).map_or(1, |a| a.parse().unwrap()),
+            walls_only: args.get(4).map(|a| a.as_str()) == Some("walls"),
+        };
+        print_histogram(&txt, rule);
+        return;
+    }
+
     println!("This is {}", day_x);
     println!("Part 1: {:?}", part1(&txt));
     println!("Part 2: {:?}", part2(&txt));
@@ -0,0 +1,395 @@
+use crate::bignum::BigUint;
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+pub struct Point {
+    pub i: isize,
+    pub j: isize,
+}
+
+// The directional buttons and the way each one moves the arm
+pub const DIRS: [(char, Point); 4] = [
+    ('^', Point { i: -1, j: 0 }),
+    ('v', Point { i: 1, j: 0 }),
+    ('<', Point { i: 0, j: -1 }),
+    ('>', Point { i: 0, j: 1 }),
+];
+
+// Every arm starts on, and presses with, this button
+pub const ACTIVATE: char = 'A';
+
+impl std::ops::Add<Point> for Point {
+    type Output = Point;
+
+    fn add(self, other: Point) -> Point {
+        Point {
+            i: self.i + other.i,
+            j: self.j + other.j,
+        }
+    }
+}
+
+// A grid of buttons, with ' ' marking gaps the arm may not hover over
+#[derive(Debug, Clone)]
+pub struct Keypad {
+    rows: Vec<Vec<char>>,
+}
+
+impl Keypad {
+    pub fn new(rows: &[&str]) -> Self {
+        Keypad {
+            rows: rows.iter().map(|r| r.chars().collect()).collect(),
+        }
+    }
+
+    pub fn numeric() -> Self {
+        Keypad::new(&["789", "456", "123", " 0A"])
+    }
+
+    pub fn directional() -> Self {
+        Keypad::new(&[" ^A", "<v>"])
+    }
+
+    // The button at p, or None for gaps and anything off the pad
+    pub fn get(&self, p: Point) -> Option<char> {
+        if p.i < 0 || p.j < 0 {
+            return None;
+        }
+        self.rows
+            .get(p.i as usize)
+            .and_then(|row| row.get(p.j as usize))
+            .copied()
+            .filter(|&c| c != ' ')
+    }
+
+    pub fn find(&self, button: char) -> Option<Point> {
+        self.buttons().find(|&(_, c)| c == button).map(|(p, _)| p)
+    }
+
+    pub fn buttons(&self) -> impl Iterator<Item = (Point, char)> + '_ {
+        self.rows.iter().enumerate().flat_map(|(i, row)| {
+            row.iter()
+                .enumerate()
+                .filter(|(_, &c)| c != ' ')
+                .map(move |(j, &c)| {
+                    (
+                        Point {
+                            i: i as isize,
+                            j: j as isize,
+                        },
+                        c,
+                    )
+                })
+        })
+    }
+}
+
+// Costs that can be summed and compared in the layer tables: u64 with
+// overflow detection, or BigUint for chains too deep for that.
+pub trait Cost: Clone + Ord {
+    fn zero() -> Self;
+    fn one() -> Self;
+    fn checked_add(&self, other: &Self) -> Option<Self>;
+}
+
+impl Cost for u64 {
+    fn zero() -> Self {
+        0
+    }
+
+    fn one() -> Self {
+        1
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        u64::checked_add(*self, *other)
+    }
+}
+
+impl Cost for BigUint {
+    fn zero() -> Self {
+        BigUint::zero()
+    }
+
+    fn one() -> Self {
+        BigUint::from(1)
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        Some(self + other)
+    }
+}
+
+// Some cost at this layer (or a code's total, at layer 0) didn't fit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Overflow {
+    pub layer: usize,
+}
+
+// A pad reduced to button ids: `buttons[id]` is the label, and
+// `moves[id]` lists (control button id, neighbouring button id) for each
+// direction the arm can move from there without hovering a gap.
+#[derive(Debug, Clone)]
+struct PadGraph {
+    buttons: Vec<char>,
+    moves: Vec<Vec<(usize, usize)>>,
+}
+
+impl PadGraph {
+    fn new(pad: &Keypad, control: &Keypad) -> Self {
+        let buttons: Vec<char> = pad.buttons().map(|(_, c)| c).collect();
+        let control_ids: Vec<char> = control.buttons().map(|(_, c)| c).collect();
+        let moves = pad
+            .buttons()
+            .map(|(p, _)| {
+                DIRS.iter()
+                    .filter_map(|&(d, delta)| {
+                        let to = pad.get(p + delta)?;
+                        Some((
+                            control_ids.iter().position(|&c| c == d).unwrap(),
+                            buttons.iter().position(|&c| c == to).unwrap(),
+                        ))
+                    })
+                    .collect()
+            })
+            .collect();
+        PadGraph { buttons, moves }
+    }
+
+    fn id(&self, button: char) -> usize {
+        self.buttons
+            .iter()
+            .position(|&c| c == button)
+            .unwrap_or_else(|| panic!("no button '{}'", button))
+    }
+}
+
+// Press costs for one layer, flat n x n over button ids and indexed
+// [from * n + to]. None means `to` can't be reached from `from`.
+type Table<C> = Vec<Option<C>>;
+
+// One Dijkstra search from button `from`
+struct Search<C> {
+    // cost of each (position, upper arm) state, flat as [pos * nc + arm]
+    dist: Vec<Option<C>>,
+    prev: Vec<Option<usize>>,
+    // some sums didn't fit in C and were dropped
+    overflowed: bool,
+}
+
+// Dijkstra over (arm position, button the arm one layer up is on), since
+// the cost of each move depends on where that upper arm is. `upper` is
+// the upper layer's table over control buttons. Exploring all those
+// states, not just the monotone paths, keeps it exact for pads of any
+// shape.
+fn search<C: Cost>(
+    pad: &PadGraph,
+    nc: usize,
+    activate: usize,
+    upper: &Table<C>,
+    from: usize,
+) -> Search<C> {
+    let mut dist: Vec<Option<C>> = vec![None; pad.buttons.len() * nc];
+    let mut prev = vec![None; pad.buttons.len() * nc];
+    let mut overflowed = false;
+    let mut heap = BinaryHeap::new();
+
+    let start = from * nc + activate;
+    dist[start] = Some(C::zero());
+    heap.push(Reverse((C::zero(), start)));
+    while let Some(Reverse((d, state))) = heap.pop() {
+        if dist[state].as_ref().is_some_and(|old| *old < d) {
+            continue;
+        }
+        let (pos, arm) = (state / nc, state % nc);
+        for &(button, next) in &pad.moves[pos] {
+            let Some(step) = &upper[arm * nc + button] else {
+                continue;
+            };
+            let Some(nd) = d.checked_add(step) else {
+                overflowed = true;
+                continue;
+            };
+            let next_state = next * nc + button;
+            if dist[next_state].as_ref().is_none_or(|old| nd < *old) {
+                dist[next_state] = Some(nd.clone());
+                prev[next_state] = Some(state);
+                heap.push(Reverse((nd, next_state)));
+            }
+        }
+    }
+    Search {
+        dist,
+        prev,
+        overflowed,
+    }
+}
+
+// A code is typed on `target` by a robot, which is driven from a
+// directional `control` pad by another robot, and so on for `robots`
+// control pads. The human presses the last control pad directly.
+//
+// Layer 0 is the target pad, layers 1..=robots are the control pads, and
+// layer robots + 1 is the human, for whom every press costs 1. The u64
+// tables for every layer are built up front, from the human down.
+pub struct KeypadChain {
+    target: PadGraph,
+    control: PadGraph,
+    // id of A on the control pad
+    activate: usize,
+    robots: usize,
+    tables: Result<Vec<Table<u64>>, Overflow>,
+}
+
+impl KeypadChain {
+    pub fn new(target: Keypad, control: Keypad, robots: usize) -> Self {
+        for b in DIRS.iter().map(|&(c, _)| c).chain([ACTIVATE]) {
+            assert!(control.find(b).is_some(), "control pad has no '{}'", b);
+        }
+        assert!(
+            target.find(ACTIVATE).is_some(),
+            "target pad has no '{}'",
+            ACTIVATE
+        );
+        let control_graph = PadGraph::new(&control, &control);
+        let mut chain = KeypadChain {
+            target: PadGraph::new(&target, &control),
+            activate: control_graph.id(ACTIVATE),
+            control: control_graph,
+            robots,
+            tables: Ok(vec![]),
+        };
+        chain.tables = chain.build_tables();
+        chain
+    }
+
+    fn pad(&self, layer: usize) -> &PadGraph {
+        if layer == 0 {
+            &self.target
+        } else {
+            &self.control
+        }
+    }
+
+    // tables[layer] for every layer 0..=robots + 1
+    fn build_tables<C: Cost>(&self) -> Result<Vec<Table<C>>, Overflow> {
+        let nc = self.control.buttons.len();
+        let mut tables = vec![vec![Some(C::one()); nc * nc]];
+        for layer in (0..=self.robots).rev() {
+            let table = self.build_table(layer, tables.last().unwrap())?;
+            tables.push(table);
+        }
+        tables.reverse();
+        Ok(tables)
+    }
+
+    fn build_table<C: Cost>(&self, layer: usize, upper: &Table<C>) -> Result<Table<C>, Overflow> {
+        let pad = self.pad(layer);
+        let (n, nc) = (pad.buttons.len(), self.control.buttons.len());
+        let mut table = vec![None; n * n];
+        for from in 0..n {
+            let search = search(pad, nc, self.activate, upper, from);
+            let mut overflowed = search.overflowed;
+            for to in 0..n {
+                // finish by pressing A one layer up, wherever that arm is
+                for arm in 0..nc {
+                    let (Some(d), Some(press)) = (
+                        &search.dist[to * nc + arm],
+                        &upper[arm * nc + self.activate],
+                    ) else {
+                        continue;
+                    };
+                    let Some(total) = d.checked_add(press) else {
+                        overflowed = true;
+                        continue;
+                    };
+                    let best: &mut Option<C> = &mut table[from * n + to];
+                    if best.as_ref().is_none_or(|b| total < *b) {
+                        *best = Some(total);
+                    }
+                }
+                if table[from * n + to].is_none() && overflowed {
+                    return Err(Overflow { layer });
+                }
+            }
+        }
+        Ok(table)
+    }
+
+    fn tables(&self) -> &[Table<u64>] {
+        self.tables
+            .as_ref()
+            .unwrap_or_else(|o| panic!("u64 costs overflow at layer {}", o.layer))
+    }
+
+    fn sum_code<C: Cost>(&self, table: &Table<C>, code: &str) -> Option<C> {
+        let n = self.target.buttons.len();
+        let mut from = self.target.id(ACTIVATE);
+        let mut total = C::zero();
+        for c in code.chars() {
+            let to = self.target.id(c);
+            let cost = table[from * n + to]
+                .as_ref()
+                .unwrap_or_else(|| panic!("no route to '{}'", c));
+            total = total.checked_add(cost)?;
+            from = to;
+        }
+        Some(total)
+    }
+
+    // Length of the shortest human sequence that types `code`
+    pub fn code_len(&self, code: &str) -> Result<u64, Overflow> {
+        let tables = self.tables.as_ref().map_err(|&o| o)?;
+        self.sum_code(&tables[0], code).ok_or(Overflow { layer: 0 })
+    }
+
+    // Same, for chains too deep for u64. The tables are rebuilt with
+    // BigUint costs on every call.
+    pub fn code_len_big(&self, code: &str) -> BigUint {
+        let tables = self.build_tables::<BigUint>().unwrap();
+        self.sum_code(&tables[0], code).unwrap()
+    }
+
+    // Cheapest way to move the arm at `layer` from `from` to `to` and
+    // press it, as the buttons to press one layer up (ending with A)
+    fn route(&self, layer: usize, from: usize, to: usize) -> Vec<char> {
+        let nc = self.control.buttons.len();
+        let upper = &self.tables()[layer + 1];
+        let search = search(self.pad(layer), nc, self.activate, upper, from);
+
+        let mut state = (0..nc)
+            .filter_map(|arm| {
+                let d = search.dist[to * nc + arm]?;
+                Some((d + upper[arm * nc + self.activate]?, to * nc + arm))
+            })
+            .min()
+            .unwrap()
+            .1;
+        let mut presses = vec![ACTIVATE];
+        while let Some(before) = search.prev[state] {
+            presses.push(self.control.buttons[state % nc]);
+            state = before;
+        }
+        presses.reverse();
+        presses
+    }
+
+    // One shortest human sequence that types `code`. Its length grows
+    // exponentially with the number of robots, so this is for checking
+    // small chains only.
+    pub fn sequence(&self, code: &str) -> String {
+        let mut presses: Vec<char> = code.chars().collect();
+        for layer in 0..=self.robots {
+            let pad = self.pad(layer);
+            let mut from = pad.id(ACTIVATE);
+            let mut above = vec![];
+            for &c in &presses {
+                let to = pad.id(c);
+                above.extend(self.route(layer, from, to));
+                from = to;
+            }
+            presses = above;
+        }
+        presses.into_iter().collect()
+    }
+}
@@ -1,111 +1,86 @@
 #![allow(non_snake_case)]
 
-use std::{
-    collections::{HashMap, HashSet, VecDeque},
-    fs,
-};
+#[path = "../common/bignum.rs"]
+mod bignum;
+mod keypad;
+mod sim;
 
-fn numeric_part(code: &str) -> usize {
+use bignum::BigUint;
+use keypad::{Keypad, KeypadChain};
+use sim::Simulator;
+use std::{env, fs};
+
+fn numeric_part(code: &str) -> u64 {
     code[..code.len() - 1].parse().unwrap()
 }
 
-// adapted from https://www.reddit.com/r/adventofcode/comments/1hj2odw/comment/m3482ai/?utm_source=share&utm_medium=web3x&utm_name=web3xcss&utm_term=1&utm_content=share_button
-fn path(p: &Vec<&str>, f: &str, t: &str) -> String {
-    let (fx, fy) = p
-        .iter()
-        .enumerate()
-        .flat_map(|(y, row)| row.chars().enumerate().map(move |(x, c)| (x, y, c)))
-        .filter(|(_, _, c)| *c == f.chars().next().unwrap())
-        .next()
-        .map(|(x, y, _)| (x, y))
-        .unwrap();
-
-    let (tx, ty) = p
-        .iter()
-        .enumerate()
-        .flat_map(|(y, row)| row.chars().enumerate().map(move |(x, c)| (x, y, c)))
-        .filter(|(_, _, c)| *c == t.chars().next().unwrap())
-        .next()
-        .map(|(x, y, _)| (x, y))
-        .unwrap();
-
-    fn g(p: &Vec<&str>, tx: usize, ty: usize, x: usize, y: usize, s: String) -> Vec<String> {
-        let mut paths = Vec::new();
-        if x == tx && y == ty {
-            paths.push(s.clone() + "A");
-        }
-        if tx < x && p[y].chars().nth(x - 1).unwrap() != ' ' {
-            let mut new_s = s.clone();
-            new_s.push('<');
-            paths.extend(g(p, tx, ty, x - 1, y, new_s));
-        }
-        if ty < y && p[y - 1].chars().nth(x).unwrap() != ' ' {
-            let mut new_s = s.clone();
-            new_s.push('^');
-            paths.extend(g(p, tx, ty, x, y - 1, new_s));
-        }
-        if ty > y && p[y + 1].chars().nth(x).unwrap() != ' ' {
-            let mut new_s = s.clone();
-            new_s.push('v');
-            paths.extend(g(p, tx, ty, x, y + 1, new_s));
-        }
-        if tx > x && p[y].chars().nth(x + 1).unwrap() != ' ' {
-            let mut new_s = s.clone();
-            new_s.push('>');
-            paths.extend(g(p, tx, ty, x + 1, y, new_s));
-        }
-        paths
-    }
-
-    g(p, tx, ty, fx, fy, String::new())
-        .into_iter()
-        .min_by_key(|p| {
-            p.chars()
-                .zip(p.chars().skip(1))
-                .filter(|(a, b)| a != b)
-                .count()
+fn complexity(txt: &str, robots: usize) -> u64 {
+    let chain = KeypadChain::new(Keypad::numeric(), Keypad::directional(), robots);
+    txt.lines()
+        .map(|c| {
+            chain
+                .code_len(c)
+                .ok()
+                .and_then(|len| len.checked_mul(numeric_part(c)))
+                .expect("complexity overflows u64")
+        })
+        .fold(0u64, |acc, x| {
+            acc.checked_add(x).expect("complexity overflows u64")
         })
-        .unwrap()
 }
 
-fn solve(s: &str, l: usize, cache: &mut HashMap<(String, usize), usize>) -> usize {
-    if l > 25 {
-        return s.len();
-    }
-    let key = (s.to_string(), l);
-    if let Some(&val) = cache.get(&key) {
-        return val;
-    }
-    let n = vec!["789", "456", "123", " 0A"];
-    let d = vec![" ^A", "<v>"];
-    let paths = (0..s.len())
-        .map(|i| {
-            let t = s.chars().nth(i).unwrap();
-            let f = if i == 0 { 'A' } else { s.chars().nth(i - 1).unwrap() };
-            let d = if l > 0 { &d } else { &n };
-            path(&d, &f.to_string(), &t.to_string())
-        })
-        .collect::<Vec<String>>();
-    let result = paths.iter().map(|path_str| solve(path_str, l + 1, cache)).sum();
-    cache.insert(key, result);
-    result
+// For chains of any depth
+fn complexity_big(txt: &str, robots: usize) -> BigUint {
+    let chain = KeypadChain::new(Keypad::numeric(), Keypad::directional(), robots);
+    txt.lines().fold(BigUint::zero(), |acc, c| {
+        &acc + &chain.code_len_big(c).mul_small(numeric_part(c) as u32)
+    })
 }
 
-fn part2(txt: &str) -> usize {
-    let codes: Vec<&str> = txt.lines().collect();
+fn part1(txt: &str) -> u64 {
+    complexity(txt, 2)
+}
 
-    let mut cache = HashMap::new();
+fn part2(txt: &str) -> u64 {
+    complexity(txt, 25)
+}
 
-    codes
-        .iter()
-        // .take(1)
-        // .map(|c| shortest_sequence_len(c, 3 + 23)
-        .map(|c| solve(c, 0, &mut cache) * numeric_part(c))
-        // .inspect(|x| println!("len: {}", x))
-        .sum()
+// The presses that type each code, checked on the simulator
+fn print_sequences(txt: &str, robots: usize) {
+    let chain = KeypadChain::new(Keypad::numeric(), Keypad::directional(), robots);
+    let sim = Simulator::new(Keypad::numeric(), Keypad::directional(), robots);
+    for code in txt.lines() {
+        let seq = chain.sequence(code);
+        let ok = if sim.validates(&seq, code) { "ok" } else { "WRONG" };
+        println!("{}: {} ({} presses, {})", code, seq, seq.len(), ok);
+    }
+}
+
+fn print_replay(robots: usize, input: &str) {
+    let sim = Simulator::new(Keypad::numeric(), Keypad::directional(), robots);
+    let replay = sim.run(input);
+    println!("input:    {}", input);
+    for (layer, pressed) in replay.pressed.iter().enumerate().rev() {
+        println!(
+            "layer {:>2}: {} (arm on {})",
+            layer, pressed, replay.arms[layer]
+        );
+    }
+    println!("Typed: {:?}", replay.typed);
+    match replay.fault {
+        Some(fault) => println!("Fault: {:?}", fault),
+        None => println!("No faults"),
+    }
 }
 
 fn main() {
+    // `cargo run --bin day21 -- replay ROBOTS SEQUENCE`
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(|a| a.as_str()) == Some("replay") {
+        print_replay(args[2].parse().unwrap(), &args[3]);
+        return;
+    }
+
     let day_x = env!("CARGO_BIN_NAME");
     let root = env!("CARGO_MANIFEST_DIR");
 
@@ -113,7 +88,57 @@ fn main() {
     // let path = String::from(root) + "/src/" + day_x + "/test1.txt";
     let txt = fs::read_to_string(path).unwrap();
 
+    // `cargo run --bin day21 -- deep ROBOTS`
+    if args.get(1).map(|a| a.as_str()) == Some("deep") {
+        let robots = args[2].parse().unwrap();
+        println!(
+            "Complexity with {} robots: {}",
+            robots,
+            complexity_big(&txt, robots)
+        );
+        return;
+    }
+
+    // `cargo run --bin day21 -- sequences ROBOTS`
+    if args.get(1).map(|a| a.as_str()) == Some("sequences") {
+        print_sequences(&txt, args[2].parse().unwrap());
+        return;
+    }
+
     println!("This is {}", day_x);
-    // println!("Part 1: {:?}", part1(&txt));
+    println!("Part 1: {:?}", part1(&txt));
     println!("Part 2: {:?}", part2(&txt));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "029A\n980A\n179A\n456A\n379A\n";
+
+    #[test]
+    fn part1_example() {
+        assert_eq!(part1(EXAMPLE), 126384);
+    }
+
+    #[test]
+    fn sequences_are_shortest_and_type_the_code() {
+        for robots in 0..=3 {
+            let chain = KeypadChain::new(Keypad::numeric(), Keypad::directional(), robots);
+            let sim = Simulator::new(Keypad::numeric(), Keypad::directional(), robots);
+            for code in EXAMPLE.lines() {
+                let seq = chain.sequence(code);
+                assert_eq!(seq.len() as u64, chain.code_len(code).unwrap());
+                assert!(sim.validates(&seq, code), "{} does not type {}", seq, code);
+            }
+        }
+    }
+
+    #[test]
+    fn big_complexity_matches_u64() {
+        assert_eq!(
+            complexity_big(EXAMPLE, 25).to_string(),
+            complexity(EXAMPLE, 25).to_string()
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use crate::keypad::{Keypad, Point, ACTIVATE, DIRS};
+
+// Replays human presses through a chain of robot arms, using the same
+// layering as `KeypadChain`: the arm at layer 0 is over the target pad,
+// arms 1..=robots are over control pads, and the human's presses drive
+// the arm at layer `robots`.
+pub struct Simulator {
+    target: Keypad,
+    control: Keypad,
+    robots: usize,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Fault {
+    // an arm was moved over a gap (or off the pad) by input press `step`
+    Gap {
+        step: usize,
+        layer: usize,
+        at: Point,
+    },
+    // a button other than <^>vA reached an arm, at input press `step`
+    UnknownButton {
+        step: usize,
+        button: char,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Replay {
+    // what ended up typed on the target pad
+    pub typed: String,
+    // the first fault, after which the replay stops
+    pub fault: Option<Fault>,
+    // pressed[layer]: every button the arm at that layer pressed, so
+    // pressed[0] == typed
+    pub pressed: Vec<String>,
+    // the button under each arm at the end
+    pub arms: Vec<char>,
+}
+
+impl Simulator {
+    pub fn new(target: Keypad, control: Keypad, robots: usize) -> Self {
+        Simulator {
+            target,
+            control,
+            robots,
+        }
+    }
+
+    fn pad(&self, layer: usize) -> &Keypad {
+        if layer == 0 {
+            &self.target
+        } else {
+            &self.control
+        }
+    }
+
+    pub fn run(&self, input: &str) -> Replay {
+        let mut arms: Vec<Point> = (0..=self.robots)
+            .map(|layer| self.pad(layer).find(ACTIVATE).unwrap())
+            .collect();
+        let mut pressed = vec![String::new(); self.robots + 1];
+        let mut fault = None;
+
+        'input: for (step, button) in input.chars().enumerate() {
+            let mut button = button;
+            for layer in (0..=self.robots).rev() {
+                if button == ACTIVATE {
+                    // the arm presses whatever it's over, which is the
+                    // input to the layer below
+                    button = self.pad(layer).get(arms[layer]).unwrap();
+                    pressed[layer].push(button);
+                    continue;
+                }
+                let Some(&(_, delta)) = DIRS.iter().find(|&&(c, _)| c == button) else {
+                    fault = Some(Fault::UnknownButton { step, button });
+                    break 'input;
+                };
+                let next = arms[layer] + delta;
+                if self.pad(layer).get(next).is_none() {
+                    fault = Some(Fault::Gap {
+                        step,
+                        layer,
+                        at: next,
+                    });
+                    break 'input;
+                }
+                arms[layer] = next;
+                break;
+            }
+        }
+
+        Replay {
+            typed: pressed[0].clone(),
+            fault,
+            pressed,
+            arms: arms
+                .iter()
+                .enumerate()
+                .map(|(layer, &p)| self.pad(layer).get(p).unwrap())
+                .collect(),
+        }
+    }
+
+    // Whether `input` types exactly `code` without ever hovering a gap
+    pub fn validates(&self, input: &str, code: &str) -> bool {
+        let replay = self.run(input);
+        replay.fault.is_none() && replay.typed == code
+    }
+}
@@ -1,61 +1,94 @@
 #![allow(non_snake_case)]
 
+mod secret;
+
 use rayon::prelude::*;
-use std::fs;
+use secret::{step_batch, SecretRng};
+use std::{env, fs};
+
+fn part1(txt: &str) -> usize {
+    let mut secrets: Vec<u32> = txt.lines().map(|l| l.parse().unwrap()).collect();
+    step_batch(&mut secrets, 2000);
+    secrets.iter().map(|&s| s as usize).sum()
+}
 
-fn hash(mut state: usize, rounds: isize) -> usize {
-    for _ in 0..rounds {
-        state = ((state << 6) ^ state) & 0xffffff;
-        state = ((state >> 5) ^ state) & 0xffffff;
-        state = ((state << 11) ^ state) & 0xffffff;
+// Every buyer's secret after `rounds` rounds, however many, by jumping
+fn print_jump(txt: &str, rounds: u64) {
+    let mut total = 0;
+    for line in txt.lines() {
+        let mut rng = SecretRng::new(line.parse().unwrap());
+        rng.jump(rounds);
+        println!("{}: {}", line, rng.state());
+        total += rng.state() as u64;
     }
-    state
+    println!("Sum after {} rounds: {}", rounds, total);
 }
 
-fn buy_price(mut state: usize, rounds: isize, seq: [isize; 4]) -> usize {
-    let mut prev_price = state % 10;
+// A window of 4 price changes, each -9..=9, packed as a base-19 index
+const NSEQ: usize = 19 * 19 * 19 * 19;
 
-    let mut last4changes = [0; 4];
-    for i in 0..rounds {
-        state = hash(state, 1);
-        let curr_price = state % 10;
-        last4changes[3] = curr_price as isize - prev_price as isize;
-        if last4changes == seq && i >= 3 {
-                return curr_price;
+fn seq_from_index(mut idx: usize) -> [isize; 4] {
+    let mut seq = [0; 4];
+    for c in seq.iter_mut().rev() {
+        *c = (idx % 19) as isize - 9;
+        idx /= 19;
+    }
+    seq
+}
+
+// Adds this buyer's price at the first occurrence of each change window
+// into `totals`. `seen` holds the last buyer id that recorded each window.
+fn record_buyer(rng: SecretRng, rounds: usize, id: u32, totals: &mut [u32], seen: &mut [u32]) {
+    let mut prev_price = rng.price();
+    let mut idx = 0;
+    for (i, curr_price) in rng.prices().take(rounds).enumerate() {
+        let change = curr_price as isize - prev_price as isize;
+        // rolling base-19 window over the last 4 changes
+        idx = (idx * 19 + (change + 9) as usize) % NSEQ;
+        if i >= 3 && seen[idx] != id {
+            seen[idx] = id;
+            totals[idx] += curr_price as u32;
         }
         prev_price = curr_price;
-        last4changes[0] = last4changes[1];
-        last4changes[1] = last4changes[2];
-        last4changes[2] = last4changes[3];
     }
-    0
 }
 
-fn part1(txt: &str) -> usize {
-    txt.lines().map(|l| hash(l.parse().unwrap(), 2000)).sum()
-}
+fn part2(txt: &str) -> ([isize; 4], usize) {
+    let secrets: Vec<u32> = txt.lines().map(|l| l.parse().unwrap()).collect();
 
-fn part2(txt: &str) -> usize {
-    let secrets: Vec<usize> = txt.lines().map(|l| l.parse().unwrap()).collect();
-
-    // all combinations of 4 numbers where each is -9..=9
-    let seqs = (-9..=9).flat_map(|a| {
-        (-9..=9).flat_map(move |b| (-9..=9).flat_map(move |c| (-9..=9).map(move |d| [a, b, c, d])))
-    });
-
-    // the inverse method where you record change sequences and find
-    // the optimal one is faster, but rayon is fun!
-    seqs
-        .par_bridge()
-        .map(|seq| {
-            secrets
-                .iter()
-                .map(|&secret| buy_price(secret, 2000, seq))
-                // .inspect(|&price| println!("{:?}", price))
-                .sum()
-        })
-        .max()
-        .unwrap()
+    // the inverse method: each buyer is simulated once and records the
+    // first price for every change window it sees
+    let totals = secrets
+        .par_iter()
+        .enumerate()
+        .fold(
+            || (vec![0u32; NSEQ], vec![0u32; NSEQ]),
+            |(mut totals, mut seen), (i, &secret)| {
+                record_buyer(
+                    SecretRng::new(secret),
+                    2000,
+                    i as u32 + 1,
+                    &mut totals,
+                    &mut seen,
+                );
+                (totals, seen)
+            },
+        )
+        .map(|(totals, _)| totals)
+        .reduce(
+            || vec![0u32; NSEQ],
+            |mut a, b| {
+                a.iter_mut().zip(b).for_each(|(x, y)| *x += y);
+                a
+            },
+        );
+
+    let (best, &total) = totals
+        .iter()
+        .enumerate()
+        .max_by_key(|&(idx, &total)| (total, std::cmp::Reverse(idx)))
+        .unwrap();
+    (seq_from_index(best), total as usize)
 }
 
 fn main() {
@@ -66,7 +99,43 @@ fn main() {
     // let path = String::from(root) + "/src/" + day_x + "/test1.txt";
     let txt = fs::read_to_string(path).unwrap();
 
+    // `cargo run --bin day22 -- jump ROUNDS`
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(|a| a.as_str()) == Some("jump") {
+        print_jump(&txt, args[2].parse().unwrap());
+        return;
+    }
+
     println!("This is {}", day_x);
     println!("Part 1: {:?}", part1(&txt));
     println!("Part 2: {:?}", part2(&txt));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "1\n10\n100\n2024\n";
+
+    #[test]
+    fn part1_example() {
+        assert_eq!(part1(EXAMPLE), 37327623);
+    }
+
+    #[test]
+    fn part2_example() {
+        assert_eq!(part2("1\n2\n3\n2024\n"), ([-2, 1, -1, 3], 23));
+    }
+
+    #[test]
+    fn jump_matches_stepping() {
+        let mut secrets: Vec<u32> = EXAMPLE.lines().map(|l| l.parse().unwrap()).collect();
+        let seeds = secrets.clone();
+        step_batch(&mut secrets, 2000);
+        for (&seed, &secret) in seeds.iter().zip(&secrets) {
+            let mut rng = SecretRng::new(seed);
+            rng.jump(2000);
+            assert_eq!(rng.state(), secret);
+        }
+    }
+}
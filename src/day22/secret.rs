@@ -0,0 +1,136 @@
+// The buyers' secret-number generator: a 24-bit xorshift.
+//
+// Every round is a composition of shifts and xors, so it's linear over
+// GF(2) and can be written as a 24x24 bit matrix. Powers of that matrix
+// let us jump ahead n rounds in O(log n).
+
+const MASK: u32 = 0xffffff;
+
+pub fn step(mut state: u32) -> u32 {
+    state = ((state << 6) ^ state) & MASK;
+    state = ((state >> 5) ^ state) & MASK;
+    ((state << 11) ^ state) & MASK
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SecretRng {
+    state: u32,
+}
+
+impl SecretRng {
+    pub fn new(seed: u32) -> Self {
+        SecretRng { state: seed }
+    }
+
+    pub fn state(&self) -> u32 {
+        self.state
+    }
+
+    pub fn price(&self) -> u8 {
+        (self.state % 10) as u8
+    }
+
+    // Advance n rounds at once
+    pub fn jump(&mut self, n: u64) {
+        self.state = BitMatrix24::step().pow(n).apply(self.state);
+    }
+
+    // Prices of the following secrets (not including the current one)
+    pub fn prices(self) -> impl Iterator<Item = u8> {
+        self.map(|s| (s % 10) as u8)
+    }
+}
+
+// Yields the next secret each time
+impl Iterator for SecretRng {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        self.state = step(self.state);
+        Some(self.state)
+    }
+}
+
+// Number of generators stepped together in `step_batch`. The inner loop
+// over a fixed-size lane array has no dependencies between lanes, so it
+// compiles down to vector shifts and xors.
+const LANES: usize = 16;
+
+// Advance every state in `states` by `rounds`
+pub fn step_batch(states: &mut [u32], rounds: usize) {
+    let mut chunks = states.chunks_exact_mut(LANES);
+    for chunk in &mut chunks {
+        let mut lanes = [0u32; LANES];
+        lanes.copy_from_slice(chunk);
+        for _ in 0..rounds {
+            for s in lanes.iter_mut() {
+                *s = step(*s);
+            }
+        }
+        chunk.copy_from_slice(&lanes);
+    }
+    for s in chunks.into_remainder() {
+        for _ in 0..rounds {
+            *s = step(*s);
+        }
+    }
+}
+
+// A linear map on 24-bit vectors, stored by column: `cols[j]` is the
+// image of bit j.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitMatrix24 {
+    cols: [u32; 24],
+}
+
+impl BitMatrix24 {
+    pub fn identity() -> Self {
+        let mut cols = [0; 24];
+        for (j, c) in cols.iter_mut().enumerate() {
+            *c = 1 << j;
+        }
+        BitMatrix24 { cols }
+    }
+
+    // One round of the generator; since it's linear, its columns are
+    // just the images of the basis vectors
+    pub fn step() -> Self {
+        let mut cols = [0; 24];
+        for (j, c) in cols.iter_mut().enumerate() {
+            *c = step(1 << j);
+        }
+        BitMatrix24 { cols }
+    }
+
+    pub fn apply(&self, v: u32) -> u32 {
+        let mut v = v & MASK;
+        let mut out = 0;
+        while v != 0 {
+            out ^= self.cols[v.trailing_zeros() as usize];
+            v &= v - 1;
+        }
+        out
+    }
+
+    // self ∘ other, i.e. apply `other` first
+    pub fn mul(&self, other: &BitMatrix24) -> Self {
+        let mut cols = [0; 24];
+        for (c, &o) in cols.iter_mut().zip(&other.cols) {
+            *c = self.apply(o);
+        }
+        BitMatrix24 { cols }
+    }
+
+    pub fn pow(&self, mut n: u64) -> Self {
+        let mut result = BitMatrix24::identity();
+        let mut base = *self;
+        while n > 0 {
+            if n & 1 == 1 {
+                result = result.mul(&base);
+            }
+            base = base.mul(&base);
+            n >>= 1;
+        }
+        result
+    }
+}
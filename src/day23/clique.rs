@@ -0,0 +1,377 @@
+// Clique finding on an interned graph, given as sorted adjacency lists.
+//
+// The graph-wide structures are sparse so that this scales to graphs with
+// tens of thousands of nodes; bitsets are only built locally, indexed by
+// the later neighbours of one vertex at a time, of which there are at most
+// the degeneracy when we go in degeneracy order.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitSet {
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    pub fn new(len: usize) -> Self {
+        BitSet {
+            words: vec![0; len.div_ceil(64)],
+        }
+    }
+
+    // 0..len
+    pub fn full(len: usize) -> Self {
+        let mut set = BitSet::new(len);
+        for i in 0..len {
+            set.insert(i);
+        }
+        set
+    }
+
+    pub fn contains(&self, i: usize) -> bool {
+        self.words[i / 64] >> (i % 64) & 1 == 1
+    }
+
+    pub fn insert(&mut self, i: usize) {
+        self.words[i / 64] |= 1 << (i % 64);
+    }
+
+    pub fn remove(&mut self, i: usize) {
+        self.words[i / 64] &= !(1 << (i % 64));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|&w| w == 0)
+    }
+
+    pub fn count(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    pub fn intersection(&self, other: &BitSet) -> BitSet {
+        BitSet {
+            words: self
+                .words
+                .iter()
+                .zip(&other.words)
+                .map(|(a, b)| a & b)
+                .collect(),
+        }
+    }
+
+    pub fn intersection_count(&self, other: &BitSet) -> usize {
+        self.words
+            .iter()
+            .zip(&other.words)
+            .map(|(a, b)| (a & b).count_ones() as usize)
+            .sum()
+    }
+
+    pub fn difference(&self, other: &BitSet) -> BitSet {
+        BitSet {
+            words: self
+                .words
+                .iter()
+                .zip(&other.words)
+                .map(|(a, b)| a & !b)
+                .collect(),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(k, &w)| {
+            let mut w = w;
+            std::iter::from_fn(move || {
+                if w == 0 {
+                    return None;
+                }
+                let b = w.trailing_zeros() as usize;
+                w &= w - 1;
+                Some(k * 64 + b)
+            })
+        })
+    }
+}
+
+// Matula & Beck bucket algorithm. Returns the vertices in degeneracy order
+// (repeatedly removing a minimum-degree vertex) and the core number of
+// each vertex. O(n + m).
+pub fn degeneracy_order(adj: &[Vec<usize>]) -> (Vec<usize>, Vec<usize>) {
+    let n = adj.len();
+    let mut degree: Vec<usize> = adj.iter().map(|a| a.len()).collect();
+    let max_degree = degree.iter().copied().max().unwrap_or(0);
+
+    // vertices sorted by degree, with bucket start offsets
+    let mut bin = vec![0; max_degree + 1];
+    for &d in &degree {
+        bin[d] += 1;
+    }
+    let mut start = 0;
+    for b in bin.iter_mut() {
+        let count = *b;
+        *b = start;
+        start += count;
+    }
+    let mut vert = vec![0; n];
+    let mut pos = vec![0; n];
+    for v in 0..n {
+        pos[v] = bin[degree[v]];
+        vert[pos[v]] = v;
+        bin[degree[v]] += 1;
+    }
+    for d in (1..=max_degree).rev() {
+        bin[d] = bin[d - 1];
+    }
+    bin[0] = 0;
+
+    for i in 0..n {
+        let v = vert[i];
+        for &u in &adj[v] {
+            if degree[u] > degree[v] {
+                // move u to the front of its bucket, then shrink the bucket
+                let du = degree[u];
+                let pu = pos[u];
+                let pw = bin[du];
+                let w = vert[pw];
+                if u != w {
+                    vert.swap(pu, pw);
+                    pos[u] = pw;
+                    pos[w] = pu;
+                }
+                bin[du] += 1;
+                degree[u] -= 1;
+            }
+        }
+    }
+
+    (vert, degree)
+}
+
+// Every triangle exactly once, as node ids in increasing order.
+// Edges are oriented from lower to higher degeneracy rank, so each
+// out-neighbourhood is at most the degeneracy in size, and triangles are
+// found by intersecting the out-lists of both ends of every edge.
+pub fn triangles(adj: &[Vec<usize>]) -> Vec<[usize; 3]> {
+    let (order, _) = degeneracy_order(adj);
+    let mut rank = vec![0; adj.len()];
+    for (r, &v) in order.iter().enumerate() {
+        rank[v] = r;
+    }
+
+    let out: Vec<Vec<usize>> = adj
+        .iter()
+        .enumerate()
+        .map(|(v, nbrs)| {
+            let mut o: Vec<usize> = nbrs
+                .iter()
+                .copied()
+                .filter(|&u| rank[u] > rank[v])
+                .collect();
+            o.sort_by_key(|&u| rank[u]);
+            o
+        })
+        .collect();
+
+    let mut tris = vec![];
+    for u in 0..adj.len() {
+        for &v in &out[u] {
+            // sorted-list intersection by rank
+            let (a, b) = (&out[u], &out[v]);
+            let (mut i, mut j) = (0, 0);
+            while i < a.len() && j < b.len() {
+                match rank[a[i]].cmp(&rank[b[j]]) {
+                    std::cmp::Ordering::Less => i += 1,
+                    std::cmp::Ordering::Greater => j += 1,
+                    std::cmp::Ordering::Equal => {
+                        let mut t = [u, v, a[i]];
+                        t.sort();
+                        tris.push(t);
+                        i += 1;
+                        j += 1;
+                    }
+                }
+            }
+        }
+    }
+    tris
+}
+
+// The neighbourhood of one vertex. The candidates P (the neighbours later
+// in degeneracy order, so at most the degeneracy of them) are relabelled
+// 0..len with local bitset adjacency. The already-processed neighbours X
+// only matter through which candidates they're adjacent to, so each is
+// kept as just that bitset over P: deg x degeneracy bits in all.
+struct Local {
+    nodes: Vec<usize>,
+    adj: Vec<BitSet>,
+    // for each vertex of X, its neighbours in P
+    x_adj: Vec<BitSet>,
+}
+
+// Where the search stands: the candidates, the candidates already tried
+// (X within P), and the vertices of the outer X still adjacent to all of R
+#[derive(Clone)]
+struct Frontier {
+    p: BitSet,
+    x: BitSet,
+    outer: Vec<usize>,
+}
+
+impl Local {
+    fn build(adj: &[Vec<usize>], v: usize, rank: &[usize], slot: &mut [usize]) -> Self {
+        let nodes: Vec<usize> = adj[v]
+            .iter()
+            .copied()
+            .filter(|&u| rank[u] > rank[v])
+            .collect();
+        for (k, &u) in nodes.iter().enumerate() {
+            slot[u] = k;
+        }
+
+        // u's neighbours among the candidates, walking whichever of the
+        // two lists is shorter
+        let len = nodes.len();
+        let in_p = |u: usize| {
+            let mut set = BitSet::new(len);
+            if adj[u].len() <= len {
+                for &w in &adj[u] {
+                    if slot[w] != usize::MAX {
+                        set.insert(slot[w]);
+                    }
+                }
+            } else {
+                for (k, w) in nodes.iter().enumerate() {
+                    if adj[u].binary_search(w).is_ok() {
+                        set.insert(k);
+                    }
+                }
+            }
+            set
+        };
+        let local_adj = nodes.iter().map(|&u| in_p(u)).collect();
+        let x_adj = adj[v]
+            .iter()
+            .filter(|&&u| rank[u] < rank[v])
+            .map(|&u| in_p(u))
+            .collect();
+
+        for &u in &nodes {
+            slot[u] = usize::MAX;
+        }
+        Local {
+            nodes,
+            adj: local_adj,
+            x_adj,
+        }
+    }
+
+    fn start(&self) -> Frontier {
+        Frontier {
+            p: BitSet::full(self.nodes.len()),
+            x: BitSet::new(self.nodes.len()),
+            outer: (0..self.x_adj.len()).collect(),
+        }
+    }
+
+    // Tomita pivot: the vertex of P ∪ X with the most neighbours in P, as
+    // its neighbours in P
+    fn pivot(&self, f: &Frontier) -> Option<&BitSet> {
+        f.p.iter()
+            .chain(f.x.iter())
+            .map(|u| &self.adj[u])
+            .chain(f.outer.iter().map(|&w| &self.x_adj[w]))
+            .max_by_key(|nbrs| f.p.intersection_count(nbrs))
+    }
+
+    // The frontier after adding candidate v to R
+    fn branch(&self, f: &Frontier, v: usize) -> Frontier {
+        Frontier {
+            p: f.p.intersection(&self.adj[v]),
+            x: f.x.intersection(&self.adj[v]),
+            outer: f
+                .outer
+                .iter()
+                .copied()
+                .filter(|&w| self.x_adj[w].contains(v))
+                .collect(),
+        }
+    }
+
+    fn expand(&self, r: &mut Vec<usize>, mut f: Frontier, visit: &mut impl FnMut(&[usize])) {
+        let Some(pivot) = self.pivot(&f) else {
+            visit(r);
+            return;
+        };
+        for v in f.p.difference(pivot).iter() {
+            r.push(self.nodes[v]);
+            self.expand(r, self.branch(&f, v), visit);
+            r.pop();
+            f.p.remove(v);
+            f.x.insert(v);
+        }
+    }
+
+    fn expand_max(&self, r: &mut Vec<usize>, mut f: Frontier, best: &mut Vec<usize>) {
+        if r.len() + f.p.count() <= best.len() {
+            return;
+        }
+        if f.p.is_empty() {
+            *best = r.clone();
+            return;
+        }
+        let pivot = self.pivot(&f).unwrap();
+        for v in f.p.difference(pivot).iter() {
+            r.push(self.nodes[v]);
+            self.expand_max(r, self.branch(&f, v), best);
+            r.pop();
+            f.p.remove(v);
+            f.x.insert(v);
+            if r.len() + f.p.count() <= best.len() {
+                return;
+            }
+        }
+    }
+}
+
+// Runs `f` on the local subproblem of every vertex, in degeneracy order.
+fn for_each_local(adj: &[Vec<usize>], mut f: impl FnMut(usize, &Local)) {
+    let (order, _) = degeneracy_order(adj);
+    let mut rank = vec![0; adj.len()];
+    for (r, &v) in order.iter().enumerate() {
+        rank[v] = r;
+    }
+    let mut slot = vec![usize::MAX; adj.len()];
+    for &v in &order {
+        let local = Local::build(adj, v, &rank, &mut slot);
+        f(v, &local);
+    }
+}
+
+// Bron–Kerbosch with pivoting, outer loop in degeneracy order (Eppstein,
+// Löffler & Strash). Calls `visit` once for every maximal clique; the
+// slice is only valid for the duration of the call.
+pub fn for_each_maximal_clique(adj: &[Vec<usize>], mut visit: impl FnMut(&[usize])) {
+    for_each_local(adj, |v, local| {
+        let mut r = vec![v];
+        local.expand(&mut r, local.start(), &mut visit);
+    });
+}
+
+pub fn maximal_cliques(adj: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let mut cliques = vec![];
+    for_each_maximal_clique(adj, |c| cliques.push(c.to_vec()));
+    cliques
+}
+
+// Same search, but branch-and-bound on |R| + |P| against the best clique
+// found so far instead of reporting everything.
+pub fn maximum_clique(adj: &[Vec<usize>]) -> Vec<usize> {
+    let mut best = vec![];
+    for_each_local(adj, |v, local| {
+        // the clique can be at most v plus its later neighbours
+        if local.nodes.len() < best.len() {
+            return;
+        }
+        let mut r = vec![v];
+        local.expand_max(&mut r, local.start(), &mut best);
+    });
+    best
+}
@@ -1,98 +1,168 @@
 #![allow(non_snake_case)]
 
+mod clique;
+
 use std::{
-    collections::{HashMap, HashSet},
-    fs,
+    collections::{BTreeMap, HashMap},
+    env, fs,
 };
 
+// Node names are interned to ids 0..n; `adj` holds sorted neighbour lists.
 #[derive(Debug)]
 struct Graph {
-    edges: HashMap<String, HashSet<String>>,
+    names: Vec<String>,
+    ids: HashMap<String, usize>,
+    adj: Vec<Vec<usize>>,
 }
 
 impl Graph {
     fn from_txt(txt: &str) -> Self {
-        let mut edges = HashMap::new();
+        let mut graph = Graph {
+            names: vec![],
+            ids: HashMap::new(),
+            adj: vec![],
+        };
         for line in txt.lines() {
             let mut parts = line.split("-");
-            let a = parts.next().unwrap().to_string();
-            let b = parts.next().unwrap().to_string();
-            edges
-                .entry(a.clone())
-                .or_insert(HashSet::new())
-                .insert(b.clone());
-            edges
-                .entry(b.clone())
-                .or_insert(HashSet::new())
-                .insert(a.clone());
-            // self edges
-            edges
-                .entry(a.clone())
-                .or_insert(HashSet::new())
-                .insert(b.clone());
-            edges
-                .entry(b.clone())
-                .or_insert(HashSet::new())
-                .insert(a.clone());
+            let a = graph.intern(parts.next().unwrap());
+            let b = graph.intern(parts.next().unwrap());
+            graph.adj[a].push(b);
+            graph.adj[b].push(a);
         }
-        Graph { edges }
-    }
-
-    fn find_groups_of_three(&self) -> isize {
-        // find all groups of three
-        // and count how many have the letter 't'
-
-        let mut count = 0;
-        // for all triples, check if they are connected
-        let nodes: Vec<String> = self.edges.keys().map(|x| x.clone()).collect();
-        for (i,a) in nodes.iter().enumerate() {
-            for (j,b) in nodes[i..].iter().enumerate() {
-                for c in nodes[(i + j)..].iter() {
-                    if self.has_edge(a, b) && self.has_edge(b, c) && self.has_edge(c, a) {
-                        if a.starts_with("t") || b.starts_with("t") || c.starts_with("t") {
-                            // println!("{} {} {}", a, b, c);
-                            count += 1;
-                        }
-                    }
-                }
-            }
+        for nbrs in graph.adj.iter_mut() {
+            nbrs.sort();
+            nbrs.dedup();
         }
-        count
+        graph
     }
 
-    fn find_largest_clique(&self) -> Vec<String> {
-        fn is_clique(graph: &Graph, nodes: &[String]) -> bool {
-            for i in 0..nodes.len() {
-                for j in i+1..nodes.len() {
-                    if !graph.has_edge(&nodes[i], &nodes[j]) {
-                        return false;
-                    }
-                }
+    fn intern(&mut self, name: &str) -> usize {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+        let id = self.names.len();
+        self.names.push(name.to_string());
+        self.ids.insert(name.to_string(), id);
+        self.adj.push(vec![]);
+        id
+    }
+
+    fn num_edges(&self) -> usize {
+        self.adj.iter().map(|a| a.len()).sum::<usize>() / 2
+    }
+
+    // Induced subgraph on the nodes kept by `keep`, indexed by node id
+    fn induced(&self, keep: &[bool]) -> Graph {
+        let mut sub = Graph {
+            names: vec![],
+            ids: HashMap::new(),
+            adj: vec![],
+        };
+        for (v, nbrs) in self.adj.iter().enumerate() {
+            if !keep[v] {
+                continue;
+            }
+            let a = sub.intern(&self.names[v]);
+            for &u in nbrs.iter().filter(|&&u| keep[u] && u > v) {
+                let b = sub.intern(&self.names[u]);
+                sub.adj[a].push(b);
+                sub.adj[b].push(a);
             }
-            true
         }
-    
-        fn backtrack(graph: &Graph, all_nodes: &[String], start: usize, current: &mut Vec<String>, best: &mut Vec<String>) {
-            if current.len() > best.len() {
-                *best = current.clone();
+        for nbrs in sub.adj.iter_mut() {
+            nbrs.sort();
+        }
+        sub
+    }
+
+    // Induced subgraph on the nodes whose name matches `pred`
+    fn subgraph(&self, pred: impl Fn(&str) -> bool) -> Graph {
+        let keep: Vec<bool> = self.names.iter().map(|n| pred(n)).collect();
+        self.induced(&keep)
+    }
+
+    fn nodes_where(&self, pred: impl Fn(&str) -> bool) -> Vec<&str> {
+        self.names
+            .iter()
+            .map(|n| n.as_str())
+            .filter(|n| pred(n))
+            .collect()
+    }
+
+    // Triangles with at least one node matching `pred`
+    fn count_triangles_where(&self, pred: impl Fn(&str) -> bool) -> usize {
+        let matches: Vec<bool> = self.names.iter().map(|n| pred(n)).collect();
+        clique::triangles(&self.adj)
+            .iter()
+            .filter(|t| t.iter().any(|&v| matches[v]))
+            .count()
+    }
+
+    // Connected components, largest first, each sorted by name
+    fn connected_components(&self) -> Vec<Vec<&str>> {
+        let mut seen = vec![false; self.adj.len()];
+        let mut components = vec![];
+        for root in 0..self.adj.len() {
+            if seen[root] {
+                continue;
             }
-            for i in start..all_nodes.len() {
-                current.push(all_nodes[i].clone());
-                if is_clique(graph, current) {
-                    backtrack(graph, all_nodes, i+1, current, best);
+            seen[root] = true;
+            let mut stack = vec![root];
+            let mut component = vec![];
+            while let Some(v) = stack.pop() {
+                component.push(self.names[v].as_str());
+                for &u in &self.adj[v] {
+                    if !seen[u] {
+                        seen[u] = true;
+                        stack.push(u);
+                    }
                 }
-                current.pop();
             }
+            component.sort();
+            components.push(component);
         }
-    
-        let all_nodes: Vec<String> = self.edges.keys().cloned().collect();
-        let mut best = vec![];
-        backtrack(self, &all_nodes, 0, &mut vec![], &mut best);
-        best
+        components.sort_by(|a, b| b.len().cmp(&a.len()).then(a.cmp(b)));
+        components
     }
 
-    fn has_edge(&self, a: &str, b: &str) -> bool {
-        self.edges[a].contains(b)
+    // degree -> number of nodes with that degree
+    fn degree_distribution(&self) -> BTreeMap<usize, usize> {
+        let mut hist = BTreeMap::new();
+        for nbrs in &self.adj {
+            *hist.entry(nbrs.len()).or_insert(0) += 1;
+        }
+        hist
+    }
+
+    fn core_numbers(&self) -> HashMap<&str, usize> {
+        let (_, core) = clique::degeneracy_order(&self.adj);
+        self.names.iter().map(|n| n.as_str()).zip(core).collect()
+    }
+
+    fn degeneracy(&self) -> usize {
+        let (_, core) = clique::degeneracy_order(&self.adj);
+        core.into_iter().max().unwrap_or(0)
+    }
+
+    // The maximal subgraph in which every node has degree at least k
+    fn k_core(&self, k: usize) -> Graph {
+        let (_, core) = clique::degeneracy_order(&self.adj);
+        let keep: Vec<bool> = core.iter().map(|&c| c >= k).collect();
+        self.induced(&keep)
+    }
+
+    fn find_largest_clique(&self) -> Vec<String> {
+        clique::maximum_clique(&self.adj)
+            .into_iter()
+            .map(|v| self.names[v].clone())
+            .collect()
+    }
+
+    fn maximal_cliques(&self) -> Vec<Vec<String>> {
+        clique::maximal_cliques(&self.adj)
+            .into_iter()
+            .map(|c| c.into_iter().map(|v| self.names[v].clone()).collect())
+            .collect()
     }
 }
 
@@ -100,18 +170,80 @@ fn part1(txt: &str) -> isize {
     let graph = Graph::from_txt(txt);
     // println!("{:?}", graph);
 
-    graph.find_groups_of_three()
+    graph.count_triangles_where(|name| name.starts_with("t")) as isize
 }
 
 fn part2(txt: &str) -> String {
     let graph = Graph::from_txt(txt);
     let clique = graph.find_largest_clique();
-    
+
     let mut sorted_clique = clique.clone();
     sorted_clique.sort();
     sorted_clique.join(",")
 }
 
+fn print_stats(graph: &Graph, prefix: &str) {
+    let is_match = |name: &str| name.starts_with(prefix);
+
+    println!("Nodes: {}", graph.names.len());
+    println!("Edges: {}", graph.num_edges());
+    println!(
+        "Nodes starting with {:?}: {}",
+        prefix,
+        graph.nodes_where(is_match).len()
+    );
+    println!(
+        "Triangles touching those nodes: {}",
+        graph.count_triangles_where(is_match)
+    );
+
+    let components = graph.connected_components();
+    println!("Connected components: {}", components.len());
+    for component in components.iter().take(5) {
+        println!("  size {}: {}...", component.len(), component[0]);
+    }
+
+    println!("Degree distribution:");
+    for (degree, count) in graph.degree_distribution() {
+        println!("  {:>4}: {}", degree, count);
+    }
+
+    let degeneracy = graph.degeneracy();
+    println!("Degeneracy: {}", degeneracy);
+    for k in 1..=degeneracy {
+        let core = graph.k_core(k);
+        println!(
+            "  {}-core: {} nodes, {} edges",
+            k,
+            core.names.len(),
+            core.num_edges()
+        );
+    }
+
+    let sub = graph.subgraph(is_match);
+    println!(
+        "Induced subgraph on {:?}: {} nodes, {} edges, {} components",
+        prefix,
+        sub.names.len(),
+        sub.num_edges(),
+        sub.connected_components().len()
+    );
+
+    println!("Maximal cliques: {}", graph.maximal_cliques().len());
+    let mut largest = graph.find_largest_clique();
+    largest.sort();
+    let core = graph.core_numbers();
+    println!(
+        "Largest clique ({}): {}",
+        largest.len(),
+        largest
+            .iter()
+            .map(|n| format!("{}[core {}]", n, core[n.as_str()]))
+            .collect::<Vec<_>>()
+            .join(",")
+    );
+}
+
 fn main() {
     let day_x = env!("CARGO_BIN_NAME");
     let root = env!("CARGO_MANIFEST_DIR");
@@ -120,6 +252,14 @@ fn main() {
     // let path = String::from(root) + "/src/" + day_x + "/test1.txt";
     let txt = fs::read_to_string(path).unwrap();
 
+    // `cargo run --bin day23 -- stats [PREFIX]`
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(|a| a.as_str()) == Some("stats") {
+        let prefix = args.get(2).map(|a| a.as_str()).unwrap_or("");
+        print_stats(&Graph::from_txt(&txt), prefix);
+        return;
+    }
+
     println!("This is {}", day_x);
     println!("Part 1: {:?}", part1(&txt));
     println!("Part 2: {:?}", part2(&txt));